@@ -0,0 +1,63 @@
+use std::fmt::Debug;
+
+use tcod::colors;
+
+use game::*;
+use game::object::*;
+use game::state::*;
+use game::pathfinding::*;
+
+//Anything that can decide what a monster does on its turn.
+//The ai is taken out of its object while it runs so it gets full access to the game state.
+pub trait Ai: Debug {
+    //used to save the ai and to look it back up with ai_from_name()
+    fn name(&self) -> &'static str;
+
+    fn take_turn(&mut self, monster_id: usize, state: &mut GameState);
+}
+
+pub fn ai_from_name(name: &str) -> Option<Box<dyn Ai>> {
+    match name {
+        "basic" => Some(Box::new(BasicMonster)),
+        _ => None,
+    }
+}
+
+//Chases the player while it can see them and attacks once it is next to them.
+#[derive(Debug)]
+pub struct BasicMonster;
+
+impl Ai for BasicMonster {
+    fn name(&self) -> &'static str {
+        "basic"
+    }
+
+    fn take_turn(&mut self, monster_id: usize, state: &mut GameState) {
+        // a basic monster takes its turn. If you can see it, it can see you
+        let (monster_x, monster_y) = state.objects[monster_id].pos();
+        if !state.is_in_fov(monster_x, monster_y) {
+            return;
+        }
+
+        let (player_x, player_y) = state.objects[PLAYER].pos();
+        if state.objects[monster_id].distance_to(&state.objects[PLAYER]) >= 2.0 {
+            // move towards player if far away.
+            // follow a real path when there is one, otherwise just head in their general direction
+            let options = PathOptions::new(Movement::Diagonal, Obstacles::Block);
+            let path = astar(&state.terrain, &state.objects, (monster_x, monster_y), (player_x, player_y), options);
+            // stepping into a closed door on the way opens it, which uses up the turn.
+            // the monster is in view so the player gets to see that happen
+            let bumped = match path.and_then(|path| path.first().cloned()) {
+                Some((x, y)) => move_by(monster_id, x - monster_x, y - monster_y, &mut state.map, &mut state.objects),
+                None => move_towards(monster_id, player_x, player_y, &mut state.map, &mut state.objects),
+            };
+            if let Some(message) = bumped {
+                state.messages.add(message, colors::WHITE);
+            }
+        } else if state.objects[PLAYER].fighter.map_or(false, |f| f.hp > 0) {
+            // close enough, attack! (if the player is still alive.)
+            let (monster, player) = mut_two(monster_id, PLAYER, &mut state.objects);
+            monster.attack(player, &mut state.messages);
+        }
+    }
+}
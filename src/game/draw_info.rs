@@ -24,6 +24,36 @@ impl TilesetDrawInfo {
     }
 }
 
+//How a kind of map tile looks in one render mode.
+//The background is `light` inside the fov and `dark` outside of it.
+#[derive(Clone, Copy, Debug)]
+pub struct TileDrawInfo {
+    pub glyph:char,
+    pub foreground:Color,
+    pub light:Color,
+    pub dark:Color,
+}
+
+impl TileDrawInfo {
+    pub fn new(glyph:char, foreground:Color, light:Color, dark:Color) -> TileDrawInfo {
+        TileDrawInfo{
+            glyph:glyph,
+            foreground:foreground,
+            light:light,
+            dark:dark,
+        }
+    }
+
+    //foreground and background, remembered tiles get a dimmed glyph
+    pub fn colors(&self, visible:bool) -> (Color, Color) {
+        if visible {
+            (self.foreground, self.light)
+        } else {
+            (self.foreground * 0.5, self.dark)
+        }
+    }
+}
+
 pub mod tileset {
     use game::draw_info::TilesetDrawInfo;
     use std::char;
@@ -43,16 +73,48 @@ pub mod tileset {
                 char::from_u32(258).unwrap()
             )
         };
-        pub static ref orc : TilesetDrawInfo = {
+        pub static ref scroll : TilesetDrawInfo = {
+            TilesetDrawInfo::new(
+                char::from_u32(261).unwrap()
+            )
+        };
+        pub static ref healing_potion : TilesetDrawInfo = {
+            TilesetDrawInfo::new(
+                char::from_u32(262).unwrap()
+            )
+        };
+        pub static ref sword : TilesetDrawInfo = {
             TilesetDrawInfo::new(
-                char::from_u32(259).unwrap()
+                char::from_u32(263).unwrap()
             )
         };
-        pub static ref troll : TilesetDrawInfo = {
+        pub static ref shield : TilesetDrawInfo = {
             TilesetDrawInfo::new(
-                char::from_u32(260).unwrap()
+                char::from_u32(264).unwrap()
             )
         };
+        pub static ref dagger : TilesetDrawInfo = {
+            TilesetDrawInfo::new(
+                char::from_u32(266).unwrap()
+            )
+        };
+        pub static ref stairs_down : TilesetDrawInfo = {
+            TilesetDrawInfo::new(
+                char::from_u32(265).unwrap()
+            )
+        };
+        //the tileset only has stairs going down so use the ascii char for the way back up
+        pub static ref stairs_up : TilesetDrawInfo = {
+            TilesetDrawInfo::new('<')
+        };
+        //there is no corpse tile in the tileset so just use the ascii one
+        pub static ref corpse : TilesetDrawInfo = {
+            TilesetDrawInfo {
+                char: '%',
+                foreground: colors::DARK_RED,
+                background: colors::BLACK,
+            }
+        };
     }
 
     //reference for later tilesets
@@ -80,12 +142,36 @@ pub mod ascii {
         char:'@',
         color:colors::WHITE,
     };
-    pub static orc : AsciiDrawInfo = AsciiDrawInfo{
-        char:'O',
-        color:colors::DESATURATED_GREEN,
+    pub static scroll : AsciiDrawInfo = AsciiDrawInfo {
+        char:'#',
+        color:colors::LIGHT_YELLOW,
+    };
+    pub static healing_potion : AsciiDrawInfo = AsciiDrawInfo {
+        char:'!',
+        color:colors::VIOLET,
+    };
+    pub static sword : AsciiDrawInfo = AsciiDrawInfo {
+        char:'/',
+        color:colors::SKY,
+    };
+    pub static shield : AsciiDrawInfo = AsciiDrawInfo {
+        char:'[',
+        color:colors::DARKER_ORANGE,
+    };
+    pub static dagger : AsciiDrawInfo = AsciiDrawInfo {
+        char:'-',
+        color:colors::SKY,
+    };
+    pub static stairs_down : AsciiDrawInfo = AsciiDrawInfo {
+        char:'>',
+        color:colors::WHITE,
+    };
+    pub static stairs_up : AsciiDrawInfo = AsciiDrawInfo {
+        char:'<',
+        color:colors::WHITE,
     };
-    pub static troll : AsciiDrawInfo = AsciiDrawInfo {
-        char:'T',
-        color:colors::DARKER_GREEN,
+    pub static corpse : AsciiDrawInfo = AsciiDrawInfo {
+        char:'%',
+        color:colors::DARK_RED,
     };
 }
@@ -0,0 +1,332 @@
+#![allow(dead_code)]
+
+use game::terrain::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FovAlgorithm {
+    //casts a line from the origin to every cell on the edge of the view. Same idea as tcod's FOV_BASIC.
+    Basic,
+    //recursive shadowcasting. Scans each octant row by row and skips the areas behind walls.
+    Shadowcasting,
+}
+
+//The cells that were visible from the origin when the fov was computed.
+pub struct Visibility {
+    width: i32,
+    height: i32,
+    visible: Vec<bool>,
+}
+
+impl Visibility {
+    //nothing visible. Used before the first compute.
+    pub fn empty(width: i32, height: i32) -> Self {
+        Visibility {
+            width: width,
+            height: height,
+            visible: vec![false; (width * height) as usize],
+        }
+    }
+
+    pub fn is_visible(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.visible[(y * self.width + x) as usize]
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn set_visible(&mut self, x: i32, y: i32) {
+        if self.in_bounds(x, y) {
+            self.visible[(y * self.width + x) as usize] = true;
+        }
+    }
+}
+
+/// Compute what can be seen from (x, y).
+/// A radius of 0 means the view is only limited by the map's size.
+/// With light_walls the walls that border the visible area are visible too.
+pub fn compute_fov<T: Terrain>(terrain: &T, x: i32, y: i32, radius: i32, light_walls: bool, algorithm: FovAlgorithm) -> Visibility {
+    let mut visibility = Visibility::empty(terrain.width(), terrain.height());
+    if !terrain.in_bounds(x, y) {
+        return visibility;
+    }
+
+    visibility.set_visible(x, y);
+
+    let radius = if radius > 0 { radius } else { terrain.width() + terrain.height() };
+    match algorithm {
+        FovAlgorithm::Basic => basic(terrain, &mut visibility, x, y, radius, light_walls),
+        FovAlgorithm::Shadowcasting => shadowcasting(terrain, &mut visibility, x, y, radius, light_walls),
+    }
+    visibility
+}
+
+fn in_radius(dx: i32, dy: i32, radius: i32) -> bool {
+    dx * dx + dy * dy <= radius * radius
+}
+
+fn basic<T: Terrain>(terrain: &T, visibility: &mut Visibility, origin_x: i32, origin_y: i32, radius: i32, light_walls: bool) {
+    //walk the outline of the square around the origin and cast a ray to each cell on it
+    let (min_x, max_x) = (origin_x - radius, origin_x + radius);
+    let (min_y, max_y) = (origin_y - radius, origin_y + radius);
+
+    let mut cast = |target_x: i32, target_y: i32| {
+        for (x, y) in Line::new((origin_x, origin_y), (target_x, target_y)).skip(1) {
+            if !terrain.in_bounds(x, y) || !in_radius(x - origin_x, y - origin_y, radius) {
+                return;
+            }
+            if !terrain.is_transparent(x, y) {
+                if light_walls {
+                    visibility.set_visible(x, y);
+                }
+                return;
+            }
+            visibility.set_visible(x, y);
+        }
+    };
+
+    for x in min_x .. max_x + 1 {
+        cast(x, min_y);
+        cast(x, max_y);
+    }
+    for y in min_y + 1 .. max_y {
+        cast(min_x, y);
+        cast(max_x, y);
+    }
+}
+
+//Multipliers that turn the single octant cast_light() walks into each of the 8 octants.
+const OCTANTS: [[i32; 8]; 4] = [
+    [1, 0, 0, -1, -1, 0, 0, 1],
+    [0, 1, -1, 0, 0, -1, 1, 0],
+    [0, 1, 1, 0, 0, -1, -1, 0],
+    [1, 0, 0, 1, -1, 0, 0, -1],
+];
+
+//followed
+//http://www.roguebasin.com/index.php?title=FOV_using_recursive_shadowcasting
+fn shadowcasting<T: Terrain>(terrain: &T, visibility: &mut Visibility, x: i32, y: i32, radius: i32, light_walls: bool) {
+    for octant in 0 .. 8 {
+        let transform = Octant {
+            xx: OCTANTS[0][octant],
+            xy: OCTANTS[1][octant],
+            yx: OCTANTS[2][octant],
+            yy: OCTANTS[3][octant],
+        };
+        cast_light(terrain, visibility, (x, y), 1, 1.0, 0.0, radius, light_walls, transform);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Octant {
+    xx: i32,
+    xy: i32,
+    yx: i32,
+    yy: i32,
+}
+
+fn cast_light<T: Terrain>(terrain: &T, visibility: &mut Visibility, origin: (i32, i32), row: i32,
+                          start_slope: f32, end_slope: f32, radius: i32, light_walls: bool, octant: Octant) {
+    if start_slope < end_slope {
+        return;
+    }
+
+    let mut start_slope = start_slope;
+    let mut next_start_slope = start_slope;
+
+    for distance in row .. radius + 1 {
+        let dy = -distance;
+        let mut blocked = false;
+
+        for dx in -distance .. 1 {
+            // translate the dx, dy coordinates into map coordinates
+            let x = origin.0 + dx * octant.xx + dy * octant.xy;
+            let y = origin.1 + dx * octant.yx + dy * octant.yy;
+
+            // left_slope and right_slope store the slopes of the left and right
+            // extremities of the cell we're considering
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+            if start_slope < right_slope {
+                continue;
+            } else if end_slope > left_slope {
+                break;
+            }
+
+            let opaque = !terrain.is_transparent(x, y);
+            if in_radius(dx, dy, radius) && (light_walls || !opaque) {
+                visibility.set_visible(x, y);
+            }
+
+            if blocked {
+                // we're scanning a row of blocked cells
+                if opaque {
+                    next_start_slope = right_slope;
+                } else {
+                    blocked = false;
+                    start_slope = next_start_slope;
+                }
+            } else if opaque && distance < radius {
+                // this is a blocking cell, start a child scan
+                blocked = true;
+                cast_light(terrain, visibility, origin, distance + 1, start_slope, left_slope, radius, light_walls, octant);
+                next_start_slope = right_slope;
+            }
+        }
+
+        // the row ended blocked so everything further out in this scan is in shadow
+        if blocked {
+            break;
+        }
+    }
+}
+
+//Bresenham line from start to end (both included).
+struct Line {
+    x: i32,
+    y: i32,
+    end: (i32, i32),
+    dx: i32,
+    dy: i32,
+    step_x: i32,
+    step_y: i32,
+    error: i32,
+    done: bool,
+}
+
+impl Line {
+    fn new(start: (i32, i32), end: (i32, i32)) -> Self {
+        let dx = (end.0 - start.0).abs();
+        let dy = -(end.1 - start.1).abs();
+        Line {
+            x: start.0,
+            y: start.1,
+            end: end,
+            dx: dx,
+            dy: dy,
+            step_x: if start.0 < end.0 { 1 } else { -1 },
+            step_y: if start.1 < end.1 { 1 } else { -1 },
+            error: dx + dy,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Line {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<(i32, i32)> {
+        if self.done {
+            return None;
+        }
+        let current = (self.x, self.y);
+        if current == self.end {
+            self.done = true;
+            return Some(current);
+        }
+
+        let doubled_error = 2 * self.error;
+        if doubled_error >= self.dy {
+            self.error += self.dy;
+            self.x += self.step_x;
+        }
+        if doubled_error <= self.dx {
+            self.error += self.dx;
+            self.y += self.step_y;
+        }
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::map::*;
+
+    const ALGORITHMS: [FovAlgorithm; 2] = [FovAlgorithm::Basic, FovAlgorithm::Shadowcasting];
+
+    #[test]
+    fn open_room_is_fully_visible() {
+        let map = Map::from_rows(&[
+            ".....",
+            ".....",
+            ".....",
+            ".....",
+            ".....",
+        ]);
+        for &algorithm in ALGORITHMS.iter() {
+            let fov = compute_fov(&map, 2, 2, 0, true, algorithm);
+            for y in 0 .. 5 {
+                for x in 0 .. 5 {
+                    assert!(fov.is_visible(x, y), "{:?} can't see ({}, {})", algorithm, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        let map = Map::from_rows(&[
+            ".......",
+            "...#...",
+            ".......",
+        ]);
+        for &algorithm in ALGORITHMS.iter() {
+            let fov = compute_fov(&map, 3, 2, 0, true, algorithm);
+            assert!(fov.is_visible(3, 1), "{:?}", algorithm);
+            assert!(!fov.is_visible(3, 0), "{:?}", algorithm);
+            assert!(fov.is_visible(0, 2), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn light_walls() {
+        let map = Map::from_rows(&[
+            "#####",
+            "#...#",
+            "#####",
+        ]);
+        for &algorithm in ALGORITHMS.iter() {
+            let lit = compute_fov(&map, 2, 1, 0, true, algorithm);
+            assert!(lit.is_visible(2, 0), "{:?}", algorithm);
+            assert!(lit.is_visible(4, 1), "{:?}", algorithm);
+
+            let unlit = compute_fov(&map, 2, 1, 0, false, algorithm);
+            assert!(!unlit.is_visible(2, 0), "{:?}", algorithm);
+            assert!(!unlit.is_visible(4, 1), "{:?}", algorithm);
+            assert!(unlit.is_visible(1, 1), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn radius_limits_the_view() {
+        let map = Map::from_rows(&["..........."]);
+        for &algorithm in ALGORITHMS.iter() {
+            let fov = compute_fov(&map, 0, 0, 3, true, algorithm);
+            assert!(fov.is_visible(3, 0), "{:?}", algorithm);
+            assert!(!fov.is_visible(4, 0), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn origin_is_visible_and_bounds_are_safe() {
+        let map = Map::from_rows(&["#"]);
+        for &algorithm in ALGORITHMS.iter() {
+            let fov = compute_fov(&map, 0, 0, 5, true, algorithm);
+            assert!(fov.is_visible(0, 0));
+            assert!(!fov.is_visible(-1, 0));
+            assert!(!fov.is_visible(0, 1));
+        }
+        assert!(!compute_fov(&map, 5, 5, 5, true, FovAlgorithm::Shadowcasting).is_visible(0, 0));
+    }
+
+    #[test]
+    fn lines_include_both_ends() {
+        let line: Vec<_> = Line::new((0, 0), (3, 1)).collect();
+        assert_eq!(line.first(), Some(&(0, 0)));
+        assert_eq!(line.last(), Some(&(3, 1)));
+        assert_eq!(line.len(), 4);
+        assert_eq!(Line::new((2, 2), (2, 2)).collect::<Vec<_>>(), vec![(2, 2)]);
+    }
+}
@@ -0,0 +1,158 @@
+#![allow(dead_code)]
+
+use tcod::console::*;
+use tcod::colors;
+use tcod::Color;
+
+use game::PLAYER;
+use game::state::*;
+
+//Everything drawn on the status panel under the map is a Widget.
+//The panel just stacks them top to bottom, so adding something new to it
+//means writing a Widget and pushing it onto the panel, nothing else changes.
+pub trait Widget {
+    //how many rows the widget takes up
+    fn height(&self) -> i32 {
+        1
+    }
+    fn render(&self, con: &mut Offscreen, x: i32, y: i32, width: i32, state: &GameState);
+}
+
+//A labeled bar, eg "HP: 20/30" on top of a bar that is 2/3 full.
+//`value` returns (current, maximum) for the given state.
+pub struct Bar {
+    pub name: &'static str,
+    pub value: fn(&GameState) -> (i32, i32),
+    pub bar_color: Color,
+    pub back_color: Color,
+}
+
+impl Widget for Bar {
+    fn render(&self, con: &mut Offscreen, x: i32, y: i32, width: i32, state: &GameState) {
+        let (value, maximum) = (self.value)(state);
+        render_bar(con, x, y, width, self.name, value, maximum, self.bar_color, self.back_color);
+    }
+}
+
+//A single line of text, eg "Depth: 3".
+pub struct Label {
+    pub text: fn(&GameState) -> String,
+    pub color: Color,
+}
+
+impl Widget for Label {
+    fn render(&self, con: &mut Offscreen, x: i32, y: i32, _width: i32, state: &GameState) {
+        con.set_default_foreground(self.color);
+        con.print_ex(x, y, BackgroundFlag::None, TextAlignment::Left, (self.text)(state));
+    }
+}
+
+pub fn render_bar(con: &mut Offscreen, x: i32, y: i32, total_width: i32, name: &str,
+                  value: i32, maximum: i32, bar_color: Color, back_color: Color) {
+    // render a bar (HP, experience, etc). First calculate the width of the bar
+    let bar_width = if maximum > 0 {
+        (value.max(0) as f32 / maximum as f32 * total_width as f32) as i32
+    } else {
+        0
+    };
+
+    // render the background first
+    con.set_default_background(back_color);
+    con.rect(x, y, total_width, 1, false, BackgroundFlag::Screen);
+
+    // now render the bar on top
+    con.set_default_background(bar_color);
+    if bar_width > 0 {
+        con.rect(x, y, bar_width.min(total_width), 1, false, BackgroundFlag::Screen);
+    }
+
+    // finally, some centered text with the values
+    con.set_default_foreground(colors::WHITE);
+    con.print_ex(x + total_width / 2, y, BackgroundFlag::None, TextAlignment::Center,
+                 &format!("{}: {}/{}", name, value, maximum));
+}
+
+const LEVEL_UP_BASE: i32 = 200;
+const LEVEL_UP_FACTOR: i32 = 150;
+
+//Turns the total xp into (level, xp into that level, xp needed for the next one).
+//Every level needs LEVEL_UP_FACTOR more xp than the one before it.
+pub fn level_progress(xp: i32) -> (i32, i32, i32) {
+    let mut level = 1;
+    let mut xp = xp;
+    loop {
+        let needed = LEVEL_UP_BASE + level * LEVEL_UP_FACTOR;
+        if xp < needed {
+            return (level, xp, needed);
+        }
+        xp -= needed;
+        level += 1;
+    }
+}
+
+fn player_hp(state: &GameState) -> (i32, i32) {
+    state.objects[PLAYER].fighter.map_or((0, 0), |f| (f.hp, f.max_hp))
+}
+
+fn player_xp(state: &GameState) -> (i32, i32) {
+    let xp = state.objects[PLAYER].fighter.map_or(0, |f| f.xp);
+    let (_, into_level, needed) = level_progress(xp);
+    (into_level, needed)
+}
+
+pub struct StatusPanel {
+    widgets: Vec<Box<dyn Widget>>,
+}
+
+impl StatusPanel {
+    pub fn new() -> Self {
+        StatusPanel { widgets: vec![] }
+    }
+
+    //The hp and xp bars followed by the depth and turn counter
+    pub fn with_default_widgets() -> Self {
+        let mut panel = StatusPanel::new();
+        panel.push(Bar {
+            name: "HP",
+            value: player_hp,
+            bar_color: colors::LIGHT_RED,
+            back_color: colors::DARKER_RED,
+        });
+        panel.push(Bar {
+            name: "XP",
+            value: player_xp,
+            bar_color: colors::LIGHT_VIOLET,
+            back_color: colors::DARKER_VIOLET,
+        });
+        panel.push(Label {
+            text: |state| {
+                let xp = state.objects[PLAYER].fighter.map_or(0, |f| f.xp);
+                format!("Level: {}  Depth: {}", level_progress(xp).0, state.depth)
+            },
+            color: colors::WHITE,
+        });
+        panel.push(Label {
+            text: |state| format!("Turn: {}", state.tick),
+            color: colors::LIGHT_GREY,
+        });
+        panel
+    }
+
+    pub fn push<W: Widget + 'static>(&mut self, widget: W) {
+        self.widgets.push(Box::new(widget));
+    }
+
+    //Draws the widgets one under the other starting at (x, y).
+    //Widgets that would go past the bottom of the console are skipped.
+    pub fn render(&self, con: &mut Offscreen, x: i32, y: i32, width: i32, state: &GameState) {
+        let mut y = y;
+        for widget in &self.widgets {
+            if y + widget.height() > con.height() {
+                break;
+            }
+            widget.render(con, x, y, width, state);
+            y += widget.height();
+        }
+    }
+}
+
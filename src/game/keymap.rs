@@ -0,0 +1,438 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use tcod::input::Key;
+use tcod::input::KeyCode;
+
+//Something the player asked for with a key press.
+//handle_keys only ever sees these, never the raw keys, so the keys can be rebound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    MoveN,
+    MoveS,
+    MoveE,
+    MoveW,
+    MoveNE,
+    MoveNW,
+    MoveSE,
+    MoveSW,
+    Wait,
+    Rest,
+    PickUp,
+    Inventory,
+    Drop,
+    Descend,
+    Ascend,
+    MessageLog,
+    ToggleFullscreen,
+    ToggleRenderMode,
+    Exit,
+}
+
+const ALL_COMMANDS: [Command; 19] = [
+    Command::MoveN, Command::MoveS, Command::MoveE, Command::MoveW,
+    Command::MoveNE, Command::MoveNW, Command::MoveSE, Command::MoveSW,
+    Command::Wait, Command::Rest, Command::PickUp, Command::Inventory, Command::Drop,
+    Command::Descend, Command::Ascend, Command::MessageLog,
+    Command::ToggleFullscreen, Command::ToggleRenderMode, Command::Exit,
+];
+
+impl Command {
+    //the name used for the command in the keymap file
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::MoveN => "move_n",
+            Command::MoveS => "move_s",
+            Command::MoveE => "move_e",
+            Command::MoveW => "move_w",
+            Command::MoveNE => "move_ne",
+            Command::MoveNW => "move_nw",
+            Command::MoveSE => "move_se",
+            Command::MoveSW => "move_sw",
+            Command::Wait => "wait",
+            Command::Rest => "rest",
+            Command::PickUp => "pick_up",
+            Command::Inventory => "inventory",
+            Command::Drop => "drop",
+            Command::Descend => "descend",
+            Command::Ascend => "ascend",
+            Command::MessageLog => "message_log",
+            Command::ToggleFullscreen => "toggle_fullscreen",
+            Command::ToggleRenderMode => "toggle_render_mode",
+            Command::Exit => "exit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Command> {
+        ALL_COMMANDS.iter().cloned().find(|command| command.name() == name)
+    }
+
+    //which way a move command goes, None for everything else
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            Command::MoveN => Some((0, -1)),
+            Command::MoveS => Some((0, 1)),
+            Command::MoveE => Some((1, 0)),
+            Command::MoveW => Some((-1, 0)),
+            Command::MoveNE => Some((1, -1)),
+            Command::MoveNW => Some((-1, -1)),
+            Command::MoveSE => Some((1, 1)),
+            Command::MoveSW => Some((-1, 1)),
+            _ => None,
+        }
+    }
+}
+
+//The key part of a binding. Letters and symbols are matched on the character they type
+//(so '>' works no matter how the keyboard layout gets there), everything else on the key code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundKey {
+    Char(char),
+    Code(KeyCode),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Binding {
+    pub key: BoundKey,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl Binding {
+    fn new(key: BoundKey) -> Self {
+        Binding { key: key, alt: false, ctrl: false }
+    }
+
+    fn from_key(key: Key) -> (Binding, Binding) {
+        let by_code = Binding { key: BoundKey::Code(key.code), alt: key.alt, ctrl: key.ctrl };
+        let by_char = Binding { key: BoundKey::Char(key.printable), alt: key.alt, ctrl: key.ctrl };
+        (by_code, by_char)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "alt+")?;
+        }
+        match self.key {
+            BoundKey::Char(c) => write!(f, "{}", c),
+            BoundKey::Code(code) => write!(f, "{}", key_code_name(code).unwrap_or("?")),
+        }
+    }
+}
+
+//Names of the non-character keys as they are written in the keymap file.
+const KEY_CODE_NAMES: [(&'static str, KeyCode); 36] = [
+    ("Escape", KeyCode::Escape),
+    ("Enter", KeyCode::Enter),
+    ("Space", KeyCode::Spacebar),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("NumPad0", KeyCode::NumPad0),
+    ("NumPad1", KeyCode::NumPad1),
+    ("NumPad2", KeyCode::NumPad2),
+    ("NumPad3", KeyCode::NumPad3),
+    ("NumPad4", KeyCode::NumPad4),
+    ("NumPad5", KeyCode::NumPad5),
+    ("NumPad6", KeyCode::NumPad6),
+    ("NumPad7", KeyCode::NumPad7),
+    ("NumPad8", KeyCode::NumPad8),
+    ("NumPad9", KeyCode::NumPad9),
+    ("NumPadEnter", KeyCode::NumPadEnter),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+];
+
+fn key_code_name(code: KeyCode) -> Option<&'static str> {
+    KEY_CODE_NAMES.iter().find(|&&(_, c)| c == code).map(|&(name, _)| name)
+}
+
+//Parses things like "k", "NumPad8", "alt+Enter" or "ctrl+x".
+fn parse_binding(text: &str) -> Option<Binding> {
+    let mut alt = false;
+    let mut ctrl = false;
+    let mut rest = text;
+    loop {
+        if rest.len() > 4 && rest.starts_with("alt+") {
+            alt = true;
+            rest = &rest[4..];
+        } else if rest.len() > 5 && rest.starts_with("ctrl+") {
+            ctrl = true;
+            rest = &rest[5..];
+        } else {
+            break;
+        }
+    }
+
+    let key = if rest.chars().count() == 1 {
+        BoundKey::Char(rest.chars().next().unwrap())
+    } else {
+        match KEY_CODE_NAMES.iter().find(|&&(name, _)| name == rest) {
+            Some(&(_, code)) => BoundKey::Code(code),
+            None => return None,
+        }
+    };
+    Some(Binding { key: key, alt: alt, ctrl: ctrl })
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(io::Error),
+    //a line of the keymap file couldn't be understood
+    Parse { line: usize, reason: String },
+    //the same key ended up bound to two diffrent commands
+    Conflict { binding: String, first: Command, second: Command },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeymapError::Io(ref err) => write!(f, "could not read keymap file: {}", err),
+            KeymapError::Parse { line, ref reason } => write!(f, "keymap file line {}: {}", line, reason),
+            KeymapError::Conflict { ref binding, first, second } => {
+                write!(f, "key '{}' is bound to both {} and {}", binding, first.name(), second.name())
+            }
+        }
+    }
+}
+
+impl Error for KeymapError {}
+
+impl From<io::Error> for KeymapError {
+    fn from(err: io::Error) -> Self {
+        KeymapError::Io(err)
+    }
+}
+
+//Translates key presses into Commands.
+//There are only a few dozen bindings so a list is plenty (KeyCode can't be hashed anyway).
+pub struct Keymap {
+    bindings: Vec<(Binding, Command)>,
+}
+
+impl Keymap {
+    //Arrow keys, the numpad and vi-keys (hjkl yubn) all move.
+    pub fn with_defaults() -> Self {
+        use self::BoundKey::*;
+
+        let defaults = [
+            (Command::MoveN, vec![Code(KeyCode::Up), Code(KeyCode::NumPad8), Char('k')]),
+            (Command::MoveS, vec![Code(KeyCode::Down), Code(KeyCode::NumPad2), Char('j')]),
+            (Command::MoveE, vec![Code(KeyCode::Right), Code(KeyCode::NumPad6), Char('l')]),
+            (Command::MoveW, vec![Code(KeyCode::Left), Code(KeyCode::NumPad4), Char('h')]),
+            (Command::MoveNE, vec![Code(KeyCode::NumPad9), Char('u')]),
+            (Command::MoveNW, vec![Code(KeyCode::NumPad7), Char('y')]),
+            (Command::MoveSE, vec![Code(KeyCode::NumPad3), Char('n')]),
+            (Command::MoveSW, vec![Code(KeyCode::NumPad1), Char('b')]),
+            (Command::Wait, vec![Code(KeyCode::NumPad5), Char('.')]),
+            (Command::Rest, vec![Char('R')]),
+            (Command::PickUp, vec![Char('g')]),
+            (Command::Inventory, vec![Char('i')]),
+            (Command::Drop, vec![Char('d')]),
+            (Command::Descend, vec![Char('>')]),
+            (Command::Ascend, vec![Char('<')]),
+            (Command::MessageLog, vec![Char('m')]),
+            (Command::ToggleRenderMode, vec![Code(KeyCode::F2)]),
+            (Command::Exit, vec![Code(KeyCode::Escape)]),
+        ];
+
+        let mut bindings = vec![];
+        for &(command, ref keys) in defaults.iter() {
+            for &key in keys {
+                bindings.push((Binding::new(key), command));
+            }
+        }
+        bindings.push((Binding { key: Code(KeyCode::Enter), alt: true, ctrl: false }, Command::ToggleFullscreen));
+
+        Keymap { bindings: bindings }
+    }
+
+    //The default keymap with the commands listed in the file rebound.
+    //A missing file just means the defaults.
+    pub fn load(path: &Path) -> Result<Keymap, KeymapError> {
+        let mut keymap = Keymap::with_defaults();
+        if !path.exists() {
+            return Ok(keymap);
+        }
+
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        keymap.apply_config(&text)?;
+        Ok(keymap)
+    }
+
+    //The keymap file has one command per line, followed by every key it should be bound to:
+    //  # comments start with a hash
+    //  move_n = k Up NumPad8
+    //  toggle_fullscreen = alt+Enter
+    //A command in the file loses all of its default keys, everything else keeps them.
+    pub fn apply_config(&mut self, text: &str) -> Result<(), KeymapError> {
+        let mut rebound: Vec<(Command, Vec<Binding>)> = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parse_error = |reason: String| KeymapError::Parse { line: line_number, reason: reason };
+
+            let equals = match line.find('=') {
+                Some(equals) => equals,
+                None => return Err(parse_error("expected '<command> = <keys>'".into())),
+            };
+            let name = line[..equals].trim();
+            let command = match Command::from_name(name) {
+                Some(command) => command,
+                None => return Err(parse_error(format!("'{}' is not a command", name))),
+            };
+            if rebound.iter().any(|&(c, _)| c == command) {
+                return Err(parse_error(format!("{} is listed more than once", name)));
+            }
+
+            let mut keys = vec![];
+            for key in line[equals + 1..].split_whitespace() {
+                match parse_binding(key) {
+                    Some(binding) => keys.push(binding),
+                    None => return Err(parse_error(format!("'{}' is not a key", key))),
+                }
+            }
+            rebound.push((command, keys));
+        }
+
+        self.bindings.retain(|&(_, command)| !rebound.iter().any(|&(c, _)| c == command));
+
+        //checking against what is already bound catches both clashes within the file
+        //and clashes with defaults that weren't overridden
+        for (command, keys) in rebound {
+            for binding in keys {
+                if let Some(other) = self.get(binding) {
+                    if other != command {
+                        return Err(KeymapError::Conflict {
+                            binding: binding.to_string(),
+                            first: other,
+                            second: command,
+                        });
+                    }
+                }
+                self.bindings.push((binding, command));
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, binding: Binding) -> Option<Command> {
+        self.bindings.iter().find(|&&(b, _)| b == binding).map(|&(_, command)| command)
+    }
+
+    //Key codes win over characters so the numpad still moves when num lock types digits.
+    pub fn command_for(&self, key: Key) -> Option<Command> {
+        let (by_code, by_char) = Binding::from_key(key);
+        if key.code != KeyCode::Char {
+            if let Some(command) = self.get(by_code) {
+                return Some(command);
+            }
+        }
+        if key.printable != '\0' {
+            return self.get(by_char);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bound_to(keymap: &Keymap, key: &str) -> Option<Command> {
+        keymap.get(parse_binding(key).unwrap())
+    }
+
+    fn parse_error_line(text: &str) -> usize {
+        match Keymap::with_defaults().apply_config(text) {
+            Err(KeymapError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn overrides_replace_the_defaults() {
+        let mut keymap = Keymap::with_defaults();
+        keymap.apply_config("# walk north with w\nmove_n = w NumPad8\n").unwrap();
+        assert_eq!(bound_to(&keymap, "w"), Some(Command::MoveN));
+        assert_eq!(bound_to(&keymap, "NumPad8"), Some(Command::MoveN));
+        assert_eq!(bound_to(&keymap, "k"), None);
+        assert_eq!(bound_to(&keymap, "Up"), None);
+        //commands that aren't in the file keep their keys
+        assert_eq!(bound_to(&keymap, "j"), Some(Command::MoveS));
+    }
+
+    #[test]
+    fn modifiers_are_part_of_the_binding() {
+        let mut keymap = Keymap::with_defaults();
+        keymap.apply_config("exit = ctrl+q").unwrap();
+        assert_eq!(bound_to(&keymap, "ctrl+q"), Some(Command::Exit));
+        assert_eq!(bound_to(&keymap, "q"), None);
+    }
+
+    #[test]
+    fn one_key_for_two_commands_is_a_conflict() {
+        let mut keymap = Keymap::with_defaults();
+        match keymap.apply_config("move_n = x\nmove_s = x\n") {
+            Err(KeymapError::Conflict { first, second, .. }) => {
+                assert_eq!((first, second), (Command::MoveN, Command::MoveS));
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+
+        //same with a default that wasn't overridden
+        let mut keymap = Keymap::with_defaults();
+        match keymap.apply_config("move_n = j") {
+            Err(KeymapError::Conflict { first, second, .. }) => {
+                assert_eq!((first, second), (Command::MoveS, Command::MoveN));
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_commands() {
+        assert_eq!(parse_error_line("# comment\nfly = f\n"), 2);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert_eq!(parse_error_line("move_n = k\n\nmove_s = Banana\n"), 3);
+    }
+
+    #[test]
+    fn missing_equals() {
+        assert_eq!(parse_error_line("move_n k"), 1);
+    }
+}
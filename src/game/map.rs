@@ -9,7 +9,7 @@ use std::cmp;
 use game::rect::*;
 use game::tile::*;
 use game::object::*;
-use game::draw_info::*;
+use game::rng::GameRng;
 
 use game::is_blocked;
 
@@ -19,6 +19,10 @@ pub struct Map {
     width:i32,
     height:i32,
     out_of_bounds_tile: Tile,
+    //cells that were touched through set() / at_mut() since the last take_changes().
+    //anything that keeps its own copy of the terrain (see TerrainGrid) uses this to stay in sync.
+    dirty: Vec<bool>,
+    changes: Vec<(i32,i32)>,
 }
 
 //TODO Maybe one day we can implement an iterator over the map
@@ -37,6 +41,8 @@ impl Map {
             width:width,
             height:height,
             out_of_bounds_tile: Tile::wall(),
+            dirty: vec![false; (height * width) as usize],
+            changes: vec![],
         }
     }
 
@@ -59,16 +65,49 @@ impl Map {
         &self.tiles[self.index_at(x,y)]
     }
 
+    //We can't know what the caller does with the tile so it always counts as a change.
+    //Use set_explored() for just marking tiles as seen.
     pub fn at_mut(&mut self, x:i32, y:i32) -> &mut Tile {
         let index = self.index_at(x,y);
+        self.mark_changed(x,y);
         &mut self.tiles[index]
     }
 
     pub fn set(&mut self, x:i32, y:i32, tile:Tile){
         let index = self.index_at(x,y);
+        self.mark_changed(x,y);
         self.tiles[index] = tile;
     }
 
+    //Turns the tile into another kind without forgetting whether it was explored.
+    pub fn set_kind(&mut self, x:i32, y:i32, kind:TileKind) {
+        let explored = self.at(x,y).explored;
+        self.set(x, y, Tile { explored: explored, ..Tile::new(kind) });
+    }
+
+    //exploring doesn't change how the tile blocks anything so it isn't reported as a change.
+    pub fn set_explored(&mut self, x:i32, y:i32) {
+        let index = self.index_at(x,y);
+        self.tiles[index].explored = true;
+    }
+
+    fn mark_changed(&mut self, x:i32, y:i32) {
+        let index = self.index_at(x,y);
+        if !self.dirty[index] {
+            self.dirty[index] = true;
+            self.changes.push((x,y));
+        }
+    }
+
+    //Hands over every cell that changed since the last call and starts tracking from scratch.
+    pub fn take_changes(&mut self) -> Vec<(i32,i32)> {
+        for &(x, y) in &self.changes {
+            let index = (y * self.width + x) as usize;
+            self.dirty[index] = false;
+        }
+        ::std::mem::replace(&mut self.changes, vec![])
+    }
+
     pub fn width(&self) -> i32 {
         self.width
     }
@@ -78,7 +117,7 @@ impl Map {
     }
 
 
-    fn create_room(&mut self, room: Rect, ) {
+    pub fn create_room(&mut self, room: Rect, ) {
         for x in (room.x1 + 1) .. room.x2 {
             for y in (room.y1 + 1) .. room.y2 {
                 self.set(x,y,Tile::empty());
@@ -86,12 +125,12 @@ impl Map {
         }
     }
 
-    fn create_v_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
+    pub fn create_v_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
         for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
             self.set(x,y, Tile::empty());
         }
     }
-    fn create_h_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
+    pub fn create_h_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
         for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
             self.set(x,y, Tile::empty());
         }
@@ -100,153 +139,28 @@ impl Map {
 
 
 
-    pub fn create_random_rooms(width:i32, height:i32, objects:&mut Vec<Object>) -> (Self, (i32,i32)){
-        const ROOM_MAX_SIZE: i32 = 10;
-        const ROOM_MIN_SIZE: i32 = 6;
-        const MAX_ROOMS: i32 = 40;
-
-        //set everything to a wall first.
-        let mut map = Map::new(width,height, Tile::wall());
-
-        //our starting position will be in the first valid room's center.
-        let mut starting_position = (0, 0);
-
-        //Then "carve" the empty rooms out.
-        let mut rooms = vec![];
-
-        //save local copy of thread_rng. Mostly for readability
-        let mut rng = rand::thread_rng();
-
-        for _ in 0..MAX_ROOMS {
-            // random width and height
-            let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-            let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-            // random position without going out of the boundaries of the map
-            let x = rng.gen_range(0, map.width() - w);
-            let y = rng.gen_range(0, map.height() - h);
-            let new_room = Rect::new(x, y, w, h);
-
-            // run through the other rooms and see if they intersect with this one
-            let failed = rooms.iter().any(|other_room| new_room.intersects_with(other_room));
-
-            // this means there are no intersections, so this room is valid
-            if !failed {
-                // "carve" it to the map's tiles
-                map.create_room(new_room);
-
-                //TODO just for the hell of it make it so the player spawns randomly in the first room.
-                let (new_x, new_y) = new_room.center();
-
-                Map::place_objects(new_room, objects);
-                
-                if rooms.is_empty() {
-                    //First room since there isnt any other rooms
-                    starting_position = (new_x, new_y);
-                }else{
-                    //Non first room. 
-                    // all rooms after the first:
-                    // connect it to the previous room with a tunnel
-                    // center coordinates of the previous room
-                    let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-                    // draw a coin (random bool value -- either true or false)
-                    if rand::random() {
-                        // first move horizontally, then vertically
-                        map.create_h_tunnel(prev_x, new_x, prev_y);
-                        map.create_v_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        // first move vertically, then horizontally
-                        map.create_v_tunnel(prev_y, new_y, prev_x);
-                        map.create_h_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                rooms.push(new_room);
-            }   
-        }
-        (map, starting_position)
-    }
-
-    pub fn place_objects(room: Rect, objects: &mut Vec<Object>) {
-        let MAX_ROOM_MONSTERS = 3;
-
-        // choose random number of monsters
-        let num_monsters = rand::thread_rng().gen_range(0, MAX_ROOM_MONSTERS + 1);
-
-        for _ in 0..num_monsters {
-            // choose random spot for this monster
-            let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-            let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
-
-            let mut monster = if rand::random::<f32>() < 0.8 {  // 80% chance of getting an orc
-                // create an orc
-                Object::new(x, y, ascii::orc, *tileset::orc,"orc", true)
-            } else {
-                Object::new(x, y, ascii::troll, *tileset::troll,"troll", true)
-            };
-            monster.alive = true;
-            objects.push(monster);
-        }
-    }
-
-
-
-    //followed
-    //https://gamedevelopment.tutsplus.com/tutorials/generate-random-cave-levels-using-cellular-automata--gamedev-9664
-    pub fn create_caves(width:i32, height:i32, objects:&mut Vec<Object>) -> Self {
-
-        //set everything to a wall first.
-        let mut map = Map::new(width,height, Tile::wall());
-
-        let mut rng = rand::thread_rng();
-
-        let chance_to_be_empty = 0.46;
-
-        for tile in map.tiles.iter_mut(){
-            let chance = rng.gen::<f32>();
-            if chance < chance_to_be_empty {
-                *tile = Tile::empty(); 
-            }   
-        }
-        let sim_steps = 6;
-        for _ in 0 .. sim_steps {
-            map.caves_sim_step();
+    //Picks a random floor tile that nothing is standing on.
+    //Gives up on guessing after a while and takes the first free tile instead.
+    pub fn random_open_position(&self, objects: &[Object], rng: &mut GameRng) -> Option<(i32,i32)> {
+        for _ in 0 .. 1000 {
+            let x = rng.gen_range(0, self.width());
+            let y = rng.gen_range(0, self.height());
+            if !is_blocked(x, y, self, objects) {
+                return Some((x, y));
+            }
         }
 
-        let max_spawn_chances = 200;
-        let mut spawn_attempts = 0;
-        
-        let desired_monsters = 15;
-        let mut spawn_amount = 0;
-        
-
-        while spawn_attempts < max_spawn_chances && spawn_amount <= desired_monsters {
-            let x = rng.gen_range(0, map.width());
-            let y = rng.gen_range(0, map.height());
-
-            let tile_blocked = is_blocked(x,y, &map, objects);
-
-            if !tile_blocked {
-                
-                let mut monster = if rand::random::<f32>() < 0.8 {  // 80% chance of getting an orc
-                // create an orc
-                    Object::new(x, y, ascii::orc, *tileset::orc,"orc", true)
-                } else {
-                    Object::new(x, y, ascii::troll, *tileset::troll,"troll", true)
-                };
-                monster.alive = true;
-                objects.push(monster);
-                spawn_amount +=1;   
+        for y in 0 .. self.height() {
+            for x in 0 .. self.width() {
+                if !is_blocked(x, y, self, objects) {
+                    return Some((x, y));
+                }
             }
-            spawn_attempts +=1;
         }
-
-        println!("spawn amount: {} spawn_attempts: {}", spawn_amount, spawn_attempts);
-
-     map
-
+        None
     }
 
-    fn caves_sim_step(&mut self) {
+    pub fn caves_sim_step(&mut self) {
         //We need to create a new map since updating the map in place will cause wonky behaviours.
         //TODO from a memory perspective we could just use boolean values to represent the walls
         //this will save memory from the map allocations
@@ -315,4 +229,20 @@ impl Map {
 
     
 
-}
\ No newline at end of file
+}
+
+//Maps drawn as text for the tests. '#' is a wall, anything else is floor.
+#[cfg(test)]
+impl Map {
+    pub fn from_rows(rows: &[&str]) -> Map {
+        let mut map = Map::new(rows[0].len() as i32, rows.len() as i32, Tile::empty());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                if cell == '#' {
+                    map.set(x as i32, y as i32, Tile::wall());
+                }
+            }
+        }
+        map
+    }
+}
@@ -0,0 +1,787 @@
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use rand::Rng;
+
+use game::map::*;
+use game::rect::*;
+use game::tile::*;
+use game::rng::GameRng;
+use game::noise::Perlin;
+
+//What every map generator hands back.
+pub struct GeneratedMap {
+    pub map: Map,
+    //where the player arrives. Always a floor tile
+    pub start: (i32, i32),
+    //the rooms (or other areas) the map was built out of. Empty for maps without rooms
+    pub regions: Vec<Rect>,
+    //floor tiles that are good spots for things like stairs
+    pub spawn_points: Vec<(i32, i32)>,
+    //spots that always get a monster / item on top of the usual ones (e.g. from prefabs)
+    pub monster_spots: Vec<(i32, i32)>,
+    pub item_spots: Vec<(i32, i32)>,
+}
+
+//A way of building a level. Generators only carve the map, filling it with
+//monsters and items is left to the Spawner so every generator gets the same treatment.
+pub trait MapGenerator {
+    //what the generator is called on the command line and in save files
+    fn name(&self) -> &'static str;
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap;
+}
+
+//how many of the gaps in room walls get a door
+const DOOR_CHANCE: f32 = 0.3;
+
+pub const GENERATOR_NAMES: [&'static str; 8] = [
+    "rooms", "bsp", "caves", "drunkard", "drunkard-horizontal", "drunkard-vertical", "drunkard-mirrored", "overworld",
+];
+
+pub fn generator_from_name(name: &str) -> Option<Box<dyn MapGenerator>> {
+    match name {
+        "rooms" => Some(Box::new(RoomsGenerator)),
+        "bsp" => Some(Box::new(BspGenerator)),
+        "caves" => Some(Box::new(CavesGenerator)),
+        "drunkard" => Some(Box::new(DrunkardGenerator::new())),
+        "drunkard-horizontal" => Some(Box::new(DrunkardGenerator { symmetry: Symmetry::Horizontal, ..DrunkardGenerator::new() })),
+        "drunkard-vertical" => Some(Box::new(DrunkardGenerator { symmetry: Symmetry::Vertical, ..DrunkardGenerator::new() })),
+        "drunkard-mirrored" => Some(Box::new(DrunkardGenerator { symmetry: Symmetry::Both, ..DrunkardGenerator::new() })),
+        "overworld" => Some(Box::new(OverworldGenerator)),
+        _ => None,
+    }
+}
+
+//Every floor tile of the map.
+pub fn floor_tiles(map: &Map) -> Vec<(i32, i32)> {
+    let mut floor = vec![];
+    for y in 0 .. map.height() {
+        for x in 0 .. map.width() {
+            if !map.at(x, y).blocked {
+                floor.push((x, y));
+            }
+        }
+    }
+    floor
+}
+
+//Groups the floor into regions of tiles that can reach each other, biggest first.
+//Only orthogonal steps count so a region can be walked no matter how things move.
+//Closed doors count as floor since they can be opened.
+pub fn floor_regions(map: &Map) -> Vec<Vec<(i32, i32)>> {
+    let width = map.width();
+    let mut seen = vec![false; (map.width() * map.height()) as usize];
+    let mut regions = vec![];
+
+    for y in 0 .. map.height() {
+        for x in 0 .. map.width() {
+            if !map.at(x, y).is_passable() || seen[(y * width + x) as usize] {
+                continue;
+            }
+
+            //flood fill everything reachable from here
+            let mut region = vec![];
+            let mut queue = VecDeque::new();
+            seen[(y * width + x) as usize] = true;
+            queue.push_back((x, y));
+            while let Some((cx, cy)) = queue.pop_front() {
+                region.push((cx, cy));
+                for &(dx, dy) in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+                    let (nx, ny) = (cx + dx, cy + dy);
+                    if map.in_bounds(nx, ny) && map.at(nx, ny).is_passable() && !seen[(ny * width + nx) as usize] {
+                        seen[(ny * width + nx) as usize] = true;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            regions.push(region);
+        }
+    }
+
+    regions.sort_by(|a, b| b.len().cmp(&a.len()));
+    regions
+}
+
+//Every tile that can be walked to from start, indexed by y * width + x.
+//Uses the same rules as floor_regions().
+pub fn reachable_from(map: &Map, start: (i32, i32)) -> Vec<bool> {
+    let width = map.width();
+    let mut reachable = vec![false; (map.width() * map.height()) as usize];
+    let (x, y) = start;
+    if !map.at(x, y).is_passable() {
+        return reachable;
+    }
+
+    let mut queue = VecDeque::new();
+    reachable[(y * width + x) as usize] = true;
+    queue.push_back((x, y));
+    while let Some((cx, cy)) = queue.pop_front() {
+        for &(dx, dy) in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+            let (nx, ny) = (cx + dx, cy + dy);
+            if map.in_bounds(nx, ny) && map.at(nx, ny).is_passable() && !reachable[(ny * width + nx) as usize] {
+                reachable[(ny * width + nx) as usize] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    reachable
+}
+
+//Makes every floor tile reachable from every other one.
+//Regions smaller than min_size aren't worth keeping and get filled in with wall,
+//the rest get a tunnel dug from their closest tile to the closest tile of the biggest region.
+pub fn connect_regions(map: &mut Map, min_size: usize) {
+    let mut regions = floor_regions(map);
+    if regions.is_empty() {
+        return;
+    }
+    let main = regions.remove(0);
+
+    for region in regions {
+        if region.len() < min_size {
+            for &(x, y) in &region {
+                map.set(x, y, Tile::wall());
+            }
+            continue;
+        }
+
+        let mut closest = (region[0], main[0]);
+        let mut closest_distance = i32::max_value();
+        for &(x, y) in &region {
+            for &(mx, my) in &main {
+                let distance = (x - mx).abs() + (y - my).abs();
+                if distance < closest_distance {
+                    closest_distance = distance;
+                    closest = ((x, y), (mx, my));
+                }
+            }
+        }
+
+        let ((x, y), (mx, my)) = closest;
+        map.create_h_tunnel(x, mx, y);
+        map.create_v_tunnel(y, my, mx);
+    }
+}
+
+//Puts doors in the gaps tunnels leave in the room walls, each one with `chance` of getting one.
+//A gap only counts if it has wall on both sides, so a tunnel running along a wall doesn't get a row of doors.
+pub fn add_doors(map: &mut Map, rooms: &[Rect], chance: f32, rng: &mut GameRng) {
+    for room in rooms {
+        let mut gaps = vec![];
+        for x in room.x1 + 1 .. room.x2 {
+            gaps.push(((x, room.y1), (1, 0)));
+            gaps.push(((x, room.y2), (1, 0)));
+        }
+        for y in room.y1 + 1 .. room.y2 {
+            gaps.push(((room.x1, y), (0, 1)));
+            gaps.push(((room.x2, y), (0, 1)));
+        }
+
+        for ((x, y), (dx, dy)) in gaps {
+            let doorway = map.at(x, y).kind == TileKind::Floor
+                && map.at(x - dx, y - dy).is_wall()
+                && map.at(x + dx, y + dy).is_wall();
+            if doorway && rng.gen::<f32>() < chance {
+                map.set(x, y, Tile::new(TileKind::ClosedDoor));
+            }
+        }
+    }
+}
+
+//For maps too small to fit anything the generator would normally make: one room as big as the map allows.
+//The middle of it is always floor, even on maps too small to have a wall around it.
+fn single_room(map: &mut Map) -> Rect {
+    let room = Rect::new(0, 0, map.width() - 1, map.height() - 1);
+    map.create_room(room);
+    let (x, y) = room.center();
+    map.set(x, y, Tile::empty());
+    room
+}
+
+//The tutorial's dungeon. Random rectangular rooms joined by L-shaped tunnels.
+pub struct RoomsGenerator;
+
+impl MapGenerator for RoomsGenerator {
+    fn name(&self) -> &'static str {
+        "rooms"
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap {
+        const ROOM_MAX_SIZE: i32 = 10;
+        const ROOM_MIN_SIZE: i32 = 6;
+        const MAX_ROOMS: i32 = 40;
+
+        //set everything to a wall first.
+        let mut map = Map::new(width,height, Tile::wall());
+
+        //Then "carve" the empty rooms out.
+        let mut rooms: Vec<Rect> = vec![];
+
+        for _ in 0..MAX_ROOMS {
+            // random width and height
+            let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            // doesn't fit on the map at all
+            if w >= map.width() || h >= map.height() {
+                continue;
+            }
+            // random position without going out of the boundaries of the map
+            let x = rng.gen_range(0, map.width() - w);
+            let y = rng.gen_range(0, map.height() - h);
+            let new_room = Rect::new(x, y, w, h);
+
+            // run through the other rooms and see if they intersect with this one
+            let failed = rooms.iter().any(|other_room| new_room.intersects_with(other_room));
+
+            // this means there are no intersections, so this room is valid
+            if !failed {
+                // "carve" it to the map's tiles
+                map.create_room(new_room);
+
+                let (new_x, new_y) = new_room.center();
+
+                // all rooms after the first are connected to the previous room with a tunnel
+                if let Some(prev_room) = rooms.last() {
+                    // center coordinates of the previous room
+                    let (prev_x, prev_y) = prev_room.center();
+                    // draw a coin (random bool value -- either true or false)
+                    if rng.gen() {
+                        // first move horizontally, then vertically
+                        map.create_h_tunnel(prev_x, new_x, prev_y);
+                        map.create_v_tunnel(prev_y, new_y, new_x);
+                    } else {
+                        // first move vertically, then horizontally
+                        map.create_v_tunnel(prev_y, new_y, prev_x);
+                        map.create_h_tunnel(prev_x, new_x, new_y);
+                    }
+                }
+
+                rooms.push(new_room);
+            }
+        }
+        if rooms.is_empty() {
+            //nothing to put doors between
+            rooms.push(single_room(&mut map));
+        } else {
+            add_doors(&mut map, &rooms, DOOR_CHANCE, rng);
+        }
+
+        //the player starts in the middle of the first room
+        let start = rooms[0].center();
+        let spawn_points = rooms.iter().map(|room| room.center()).collect();
+        GeneratedMap {
+            map: map,
+            start: start,
+            regions: rooms,
+            spawn_points: spawn_points,
+            monster_spots: vec![],
+            item_spots: vec![],
+        }
+    }
+}
+
+//Binary space partitioning. The map is cut in two over and over until the pieces are
+//small enough, every piece gets a room and then the two halves of every cut are joined up.
+//Fills the map a lot more evenly than throwing rooms at it and seeing what sticks.
+pub struct BspGenerator;
+
+//pieces are never cut smaller than this, so there is always space for a room
+const BSP_MIN_LEAF_SIZE: i32 = 8;
+//and are always cut when they are bigger than this
+const BSP_MAX_LEAF_SIZE: i32 = 20;
+//the size handed to Rect::new(). create_room() digs from x1 + 1 up to (not including) x2, so the floor is 1 smaller
+const BSP_ROOM_MIN_SIZE: i32 = 5;
+
+impl BspGenerator {
+    //Splits the area (or puts a room in it if it is small enough) and returns
+    //the center of one of the rooms in it so the caller can connect to it.
+    fn split(&self, area: Rect, map: &mut Map, rooms: &mut Vec<Rect>, rng: &mut GameRng) -> (i32, i32) {
+        let width = area.x2 - area.x1;
+        let height = area.y2 - area.y1;
+        let can_cut_across_x = width >= BSP_MIN_LEAF_SIZE * 2;
+        let can_cut_across_y = height >= BSP_MIN_LEAF_SIZE * 2;
+        let too_big = width > BSP_MAX_LEAF_SIZE || height > BSP_MAX_LEAF_SIZE;
+
+        if too_big && (can_cut_across_x || can_cut_across_y) {
+            //cut the long side so the pieces don't end up as thin strips
+            let cut_x = if can_cut_across_x && can_cut_across_y {
+                if width as f32 >= height as f32 * 1.25 {
+                    true
+                } else if height as f32 >= width as f32 * 1.25 {
+                    false
+                } else {
+                    rng.gen()
+                }
+            } else {
+                can_cut_across_x
+            };
+
+            let (first, second) = if cut_x {
+                let cut = rng.gen_range(area.x1 + BSP_MIN_LEAF_SIZE, area.x2 - BSP_MIN_LEAF_SIZE + 1);
+                (Rect { x2: cut, ..area }, Rect { x1: cut, ..area })
+            } else {
+                let cut = rng.gen_range(area.y1 + BSP_MIN_LEAF_SIZE, area.y2 - BSP_MIN_LEAF_SIZE + 1);
+                (Rect { y2: cut, ..area }, Rect { y1: cut, ..area })
+            };
+
+            let (x1, y1) = self.split(first, map, rooms, rng);
+            let (x2, y2) = self.split(second, map, rooms, rng);
+            //join the two halves, the same way the rooms generator does
+            if rng.gen() {
+                map.create_h_tunnel(x1, x2, y1);
+                map.create_v_tunnel(y1, y2, x2);
+            } else {
+                map.create_v_tunnel(y1, y2, x1);
+                map.create_h_tunnel(x1, x2, y2);
+            }
+            return if rng.gen() { (x1, y1) } else { (x2, y2) };
+        }
+
+        //a leaf. Put a room of random size somewhere in it
+        let w = rng.gen_range(BSP_ROOM_MIN_SIZE, width + 1);
+        let h = rng.gen_range(BSP_ROOM_MIN_SIZE, height + 1);
+        let x = rng.gen_range(area.x1, area.x2 - w + 1);
+        let y = rng.gen_range(area.y1, area.y2 - h + 1);
+        let room = Rect::new(x, y, w, h);
+        map.create_room(room);
+        rooms.push(room);
+        room.center()
+    }
+}
+
+impl MapGenerator for BspGenerator {
+    fn name(&self) -> &'static str {
+        "bsp"
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap {
+        let mut map = Map::new(width,height, Tile::wall());
+        let mut rooms = vec![];
+
+        //create_room() carves up to x2 - 1 so leaving off the last row and column
+        //keeps a wall around the edge of the map
+        let everything = Rect::new(0, 0, width - 1, height - 1);
+        if width - 1 < BSP_ROOM_MIN_SIZE || height - 1 < BSP_ROOM_MIN_SIZE {
+            //not even one leaf fits, nothing to put doors between either
+            rooms.push(single_room(&mut map));
+        } else {
+            self.split(everything, &mut map, &mut rooms, rng);
+            add_doors(&mut map, &rooms, DOOR_CHANCE, rng);
+        }
+
+        let start = rooms[0].center();
+        let spawn_points = rooms.iter().map(|room| room.center()).collect();
+        GeneratedMap {
+            map: map,
+            start: start,
+            regions: rooms,
+            spawn_points: spawn_points,
+            monster_spots: vec![],
+            item_spots: vec![],
+        }
+    }
+}
+
+//followed
+//https://gamedevelopment.tutsplus.com/tutorials/generate-random-cave-levels-using-cellular-automata--gamedev-9664
+pub struct CavesGenerator;
+
+//caves with fewer tiles than this are filled in instead of connected
+const MIN_CAVE_SIZE: usize = 20;
+
+impl MapGenerator for CavesGenerator {
+    fn name(&self) -> &'static str {
+        "caves"
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap {
+        //set everything to a wall first.
+        let mut map = Map::new(width,height, Tile::wall());
+
+        let chance_to_be_empty = 0.46;
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let chance = rng.gen::<f32>();
+                if chance < chance_to_be_empty {
+                    map.set(x, y, Tile::empty());
+                }
+            }
+        }
+        let sim_steps = 6;
+        for _ in 0 .. sim_steps {
+            map.caves_sim_step();
+        }
+        //the automata leave little pockets all over the place that nothing could ever walk to
+        connect_regions(&mut map, MIN_CAVE_SIZE);
+
+        //everything left is one connected cave so any floor tile will do for a start
+        let spawn_points = floor_tiles(&map);
+        let start = *rng.choose(&spawn_points).expect("the caves came out without any floor");
+        GeneratedMap {
+            map: map,
+            start: start,
+            regions: vec![],
+            spawn_points: spawn_points,
+            monster_spots: vec![],
+            item_spots: vec![],
+        }
+    }
+}
+
+//Which way the drunkard's walk map is mirrored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symmetry {
+    None,
+    //left half matches the right half
+    Horizontal,
+    //top half matches the bottom half
+    Vertical,
+    Both,
+}
+
+//Drunkard's walk. Walkers stumble around at random digging out everything they step on.
+//Every walker starts on floor that was already dug and only moves orthogonally,
+//so the caverns always come out connected.
+pub struct DrunkardGenerator {
+    //how much of the map (not counting the wall around the edge) should end up as floor. 0.0 - 1.0
+    pub coverage: f32,
+    //how many walkers dig at the same time
+    pub walkers: i32,
+    //how many steps a walker takes before it stops. When they have all stopped a new batch
+    //starts from random floor tiles, until there is enough floor
+    pub lifetime: i32,
+    //every tile that is dug is also dug on the mirrored side(s)
+    pub symmetry: Symmetry,
+}
+
+impl DrunkardGenerator {
+    pub fn new() -> Self {
+        DrunkardGenerator {
+            coverage: 0.4,
+            walkers: 4,
+            lifetime: 200,
+            symmetry: Symmetry::None,
+        }
+    }
+
+    //The tile and wherever the symmetry copies it to.
+    fn mirrored(&self, x: i32, y: i32, width: i32, height: i32) -> Vec<(i32, i32)> {
+        let (mx, my) = (width - 1 - x, height - 1 - y);
+        match self.symmetry {
+            Symmetry::None => vec![(x, y)],
+            Symmetry::Horizontal => vec![(x, y), (mx, y)],
+            Symmetry::Vertical => vec![(x, y), (x, my)],
+            Symmetry::Both => vec![(x, y), (mx, y), (x, my), (mx, my)],
+        }
+    }
+
+    //Digs out the tile (and its mirrors), remembering every new floor tile.
+    fn dig(&self, map: &mut Map, x: i32, y: i32, floor: &mut Vec<(i32, i32)>) {
+        let (width, height) = (map.width(), map.height());
+        for (x, y) in self.mirrored(x, y, width, height) {
+            if map.at(x, y).blocked {
+                map.set(x, y, Tile::empty());
+                floor.push((x, y));
+            }
+        }
+    }
+}
+
+impl MapGenerator for DrunkardGenerator {
+    fn name(&self) -> &'static str {
+        //only the symmetry is told apart, other settings are back to the defaults after loading
+        match self.symmetry {
+            Symmetry::None => "drunkard",
+            Symmetry::Horizontal => "drunkard-horizontal",
+            Symmetry::Vertical => "drunkard-vertical",
+            Symmetry::Both => "drunkard-mirrored",
+        }
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap {
+        let mut map = Map::new(width,height, Tile::wall());
+        let mut floor = vec![];
+
+        //walkers stay off the outer edge so the map keeps a wall around it
+        let (min_x, max_x) = (1, width - 2);
+        let (min_y, max_y) = (1, height - 2);
+        let coverage = self.coverage.max(0.0).min(1.0);
+        let wanted = ((max_x - min_x + 1) * (max_y - min_y + 1)) as f32 * coverage;
+
+        //dig out the middle first, that's where the player starts
+        let start = (width / 2, height / 2);
+        self.dig(&mut map, start.0, start.1, &mut floor);
+
+        'digging: while (floor.len() as f32) < wanted {
+            let mut walkers: Vec<(i32, i32)> = (0 .. self.walkers.max(1))
+                .map(|_| *rng.choose(&floor).unwrap())
+                .collect();
+
+            for _ in 0 .. self.lifetime.max(1) {
+                for walker in walkers.iter_mut() {
+                    let (dx, dy) = *rng.choose(&[(0, -1), (0, 1), (-1, 0), (1, 0)]).unwrap();
+                    let x = (walker.0 + dx).max(min_x).min(max_x);
+                    let y = (walker.1 + dy).max(min_y).min(max_y);
+                    *walker = (x, y);
+                    self.dig(&mut map, x, y, &mut floor);
+                    if floor.len() as f32 >= wanted {
+                        break 'digging;
+                    }
+                }
+            }
+        }
+
+        GeneratedMap {
+            map: map,
+            start: start,
+            regions: vec![],
+            spawn_points: floor,
+            monster_spots: vec![],
+            item_spots: vec![],
+        }
+    }
+}
+
+//Outdoors. Two layers of noise, one for how high the ground is and one for how wet it is,
+//pick between water, grass, forest and mountains. Then a few small houses get dropped on open ground.
+pub struct OverworldGenerator;
+
+//how zoomed in the noise is. Smaller gives bigger lakes and forests
+const OVERWORLD_SCALE: f32 = 0.08;
+const OVERWORLD_OCTAVES: u32 = 4;
+//lower than this is water, higher than MOUNTAIN_LEVEL is mountain
+const WATER_LEVEL: f32 = -0.2;
+//how far above the water level it is still shallow water
+const SHORE_WIDTH: f32 = 0.05;
+const MOUNTAIN_LEVEL: f32 = 0.25;
+//anything wetter than this (that isn't water or mountain) grows forest
+const FOREST_LEVEL: f32 = 0.1;
+const MAX_BUILDINGS: i32 = 6;
+//includes the walls
+const BUILDING_MIN_SIZE: i32 = 5;
+const BUILDING_MAX_SIZE: i32 = 9;
+
+impl OverworldGenerator {
+    //Walls around the edge of the rect (x2 / y2 included), floor inside and a door in the middle of one side.
+    fn stamp_building(&self, map: &mut Map, building: Rect, rng: &mut GameRng) {
+        for x in building.x1 ..= building.x2 {
+            for y in building.y1 ..= building.y2 {
+                map.set(x, y, Tile::wall());
+            }
+        }
+        map.create_room(building);
+
+        let (center_x, center_y) = building.center();
+        let door = match rng.gen_range(0, 4) {
+            0 => (center_x, building.y1),
+            1 => (center_x, building.y2),
+            2 => (building.x1, center_y),
+            _ => (building.x2, center_y),
+        };
+        map.set(door.0, door.1, Tile::new(TileKind::ClosedDoor));
+    }
+}
+
+//Water and mountains can cut bits of the overworld off. Only the biggest area gets used
+//for the start and stairs so they can always be reached.
+//If there is no open ground at all (small maps can come out as one big lake) a spot in the middle is cleared.
+fn main_ground(map: &mut Map) -> Vec<(i32, i32)> {
+    if let Some(main) = floor_regions(map).into_iter().next() {
+        return main;
+    }
+    let (x, y) = (map.width() / 2, map.height() / 2);
+    map.set(x, y, Tile::new(TileKind::Grass));
+    vec![(x, y)]
+}
+
+impl MapGenerator for OverworldGenerator {
+    fn name(&self) -> &'static str {
+        "overworld"
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap {
+        let elevation = Perlin::new(rng);
+        let wetness = Perlin::new(rng);
+
+        let mut map = Map::new(width,height, Tile::new(TileKind::Grass));
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let (nx, ny) = (x as f32 * OVERWORLD_SCALE, y as f32 * OVERWORLD_SCALE);
+                let ground = elevation.fractal(nx, ny, OVERWORLD_OCTAVES, 0.5);
+                let wet = wetness.fractal(nx, ny, OVERWORLD_OCTAVES, 0.5);
+                let kind = if ground < WATER_LEVEL {
+                    TileKind::DeepWater
+                } else if ground < WATER_LEVEL + SHORE_WIDTH {
+                    TileKind::ShallowWater
+                } else if ground > MOUNTAIN_LEVEL {
+                    TileKind::Mountain
+                } else if wet > FOREST_LEVEL {
+                    TileKind::Forest
+                } else {
+                    TileKind::Grass
+                };
+                map.set(x, y, Tile::new(kind));
+            }
+        }
+
+        let mut buildings: Vec<Rect> = vec![];
+        for _ in 0 .. MAX_BUILDINGS * 50 {
+            if buildings.len() as i32 >= MAX_BUILDINGS {
+                break;
+            }
+            let w = rng.gen_range(BUILDING_MIN_SIZE, BUILDING_MAX_SIZE + 1);
+            let h = rng.gen_range(BUILDING_MIN_SIZE, BUILDING_MAX_SIZE + 1);
+            //the house and the ring around it have to fit inside the edge of the map
+            if w + 3 > width || h + 3 > height {
+                continue;
+            }
+            let x = rng.gen_range(1, width - w - 1);
+            let y = rng.gen_range(1, height - h - 1);
+            let building = Rect::new(x, y, w, h);
+
+            //houses need a walkable ring around them so the door never opens into a lake
+            //or onto another house
+            let around = Rect::new(x - 1, y - 1, w + 2, h + 2);
+            let open = (around.x1 ..= around.x2)
+                .all(|x| (around.y1 ..= around.y2).all(|y| !map.at(x, y).blocked));
+            let crowded = buildings.iter().any(|other| around.intersects_with(other));
+            if open && !crowded {
+                self.stamp_building(&mut map, building, rng);
+                buildings.push(building);
+            }
+        }
+
+        let main = main_ground(&mut map);
+        let grass: Vec<(i32, i32)> = main.iter()
+            .cloned()
+            .filter(|&(x, y)| map.at(x, y).kind == TileKind::Grass)
+            .collect();
+        let start = *rng.choose(&grass).or_else(|| rng.choose(&main)).unwrap();
+        GeneratedMap {
+            map: map,
+            start: start,
+            regions: vec![],
+            spawn_points: main,
+            monster_spots: vec![],
+            item_spots: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::rng;
+
+    #[test]
+    fn finds_separate_regions() {
+        let map = Map::from_rows(&[
+            "##########",
+            "#....#..##",
+            "#....#..##",
+            "##########",
+        ]);
+        let regions = floor_regions(&map);
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].len(), 8);
+        assert_eq!(regions[1].len(), 4);
+    }
+
+    #[test]
+    fn tunnels_pockets_into_the_main_region() {
+        let mut map = Map::from_rows(&[
+            "############",
+            "#.....######",
+            "#.....###..#",
+            "#.....###..#",
+            "############",
+        ]);
+        connect_regions(&mut map, 4);
+        let regions = floor_regions(&map);
+        assert_eq!(regions.len(), 1);
+        //both pockets are still there, just joined up
+        assert!(!map.at(1, 1).blocked);
+        assert!(!map.at(10, 3).blocked);
+    }
+
+    #[test]
+    fn fills_in_small_pockets() {
+        let mut map = Map::from_rows(&[
+            "############",
+            "#.....######",
+            "#.....###..#",
+            "#.....###..#",
+            "############",
+        ]);
+        connect_regions(&mut map, 5);
+        let regions = floor_regions(&map);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].len(), 15);
+        assert!(map.at(9, 2).blocked);
+        assert!(map.at(10, 3).blocked);
+    }
+
+    #[test]
+    fn generator_names_load_the_same_generator() {
+        for &name in GENERATOR_NAMES.iter() {
+            assert_eq!(generator_from_name(name).unwrap().name(), name);
+        }
+    }
+
+    #[test]
+    fn caves_are_one_region() {
+        for seed in 1 .. 6 {
+            let generated = CavesGenerator.generate(80, 45, &mut rng::from_seed(seed));
+            assert_eq!(floor_regions(&generated.map).len(), 1, "seed {}", seed);
+            assert!(!generated.map.at(generated.start.0, generated.start.1).blocked);
+        }
+    }
+
+    //the generator can't fit its usual rooms on these, it still has to give back somewhere to stand
+    fn check_tiny_maps(generator: &dyn MapGenerator) {
+        for &(width, height) in &[(1, 1), (2, 3), (4, 4), (7, 5), (12, 6)] {
+            for seed in 0 .. 5 {
+                let generated = generator.generate(width, height, &mut rng::from_seed(seed));
+                let (x, y) = generated.start;
+                assert!(generated.map.in_bounds(x, y), "{} start {:?} on {}x{}", generator.name(), generated.start, width, height);
+                assert!(!generated.map.at(x, y).blocked, "{} start {:?} on {}x{}", generator.name(), generated.start, width, height);
+                assert!(!generated.regions.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn rooms_fit_on_tiny_maps() {
+        check_tiny_maps(&RoomsGenerator);
+    }
+
+    #[test]
+    fn bsp_fits_on_tiny_maps() {
+        check_tiny_maps(&BspGenerator);
+    }
+
+    #[test]
+    fn overworld_fits_on_small_maps() {
+        for &(width, height) in &[(1, 1), (3, 2), (6, 6), (8, 12), (10, 10)] {
+            for seed in 0 .. 10 {
+                let generated = OverworldGenerator.generate(width, height, &mut rng::from_seed(seed));
+                let (x, y) = generated.start;
+                assert!(generated.map.in_bounds(x, y), "start {:?} on {}x{}", generated.start, width, height);
+                assert!(generated.map.at(x, y).is_passable(), "start {:?} on {}x{}", generated.start, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn clears_ground_when_there_is_none() {
+        let mut map = Map::from_rows(&[
+            "#####",
+            "#####",
+            "#####",
+        ]);
+        assert_eq!(main_ground(&mut map), vec![(2, 1)]);
+        assert_eq!(map.at(2, 1).kind, TileKind::Grass);
+    }
+}
@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+
+use tcod::Color;
+
+//Only this many messages are kept around for the history viewer.
+const MAX_MESSAGES: usize = 500;
+
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub text: String,
+    pub color: Color,
+    //the turn (GameState::tick) the message was written on
+    pub turn: i32,
+}
+
+//Everything the game wants to tell the player.
+//Shown in the panel under the map and in the full screen history.
+pub struct Messages {
+    messages: Vec<Message>,
+    //new messages get stamped with this. GameState keeps it in step with its tick
+    pub turn: i32,
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Messages { messages: vec![], turn: 0 }
+    }
+
+    /// add the new message as a (text, color) pair, stamped with the current turn
+    pub fn add<T: Into<String>>(&mut self, message: T, color: Color) {
+        let turn = self.turn;
+        self.push(Message { text: message.into(), color: color, turn: turn });
+    }
+
+    //used when loading so messages keep the turn they were written on
+    pub fn push(&mut self, message: Message) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(message);
+    }
+
+    //oldest first
+    pub fn iter(&self) -> ::std::slice::Iter<Message> {
+        self.messages.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
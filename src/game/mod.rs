@@ -3,61 +3,171 @@ extern crate tcod;
 extern crate tcod_sys;
 extern crate rand;
 
+use std::cmp;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::ptr;
+
 use tcod::console::*;
 use tcod::colors;
-use tcod::Color;
 use tcod::input::Key;
 use tcod::input::KeyCode::*;
-use tcod::map::{Map as FovMap, FovAlgorithm};
 
 mod object;
 mod tile;
 mod map;
 mod draw_info;
 mod rect;
+mod rng;
+mod state;
+mod save;
+mod ai;
+mod pathfinding;
+mod fov;
+mod terrain;
+mod messages;
+mod gui;
+mod keymap;
+mod render;
+mod monsters;
+mod spawn;
+mod mapgen;
+mod noise;
+mod prefab;
+
+pub use game::rng::random_seed;
 
 use game::object::*;
 use game::map::*;
 use game::draw_info::*;
 use game::tile::*;
-
-const COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
-const COLOR_DARK_GROUND: Color = Color { r: 50, g: 50, b: 150 };
-const COLOR_LIGHT_WALL: Color = Color { r: 130, g: 110, b: 50 };
-const COLOR_LIGHT_GROUND: Color = Color { r: 200, g: 180, b: 50 };
+use game::state::*;
+use game::messages::*;
+use game::gui::*;
+use game::keymap::*;
+use game::render::{Renderer, RenderMode, draw_map};
+use game::monsters::*;
+use game::mapgen::*;
+use game::prefab::*;
 
 const PLAYER : usize  = 0;
 
+const SCREEN_WIDTH: i32 = 80;
+const SCREEN_HEIGHT: i32 = 50;
+
+const INVENTORY_WIDTH: i32 = 50;
+
+// sizes and coordinates relevant for the GUI
+const PANEL_HEIGHT: i32 = 5;
+const PANEL_Y: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
+//the status widgets take the left side of the panel, messages the rest
+const BAR_WIDTH: i32 = 20;
+const MSG_X: i32 = BAR_WIDTH + 2;
+const MSG_WIDTH: i32 = SCREEN_WIDTH - BAR_WIDTH - 2;
+
+//how many messages page up / page down skip in the message history
+const HISTORY_PAGE: usize = 10;
+
+
+const SAVE_FILE: &'static str = "savegame";
+//optional, overrides the default key bindings. see Keymap::apply_config for the format
+const KEYMAP_FILE: &'static str = "keymap.cfg";
+const MONSTER_FILE: &'static str = "data/monsters.txt";
+//every .txt file in here is a prefab that can show up on new levels
+const PREFAB_DIR: &'static str = "data/prefabs";
+
+//The seed decides everything random about the run (map layout, player start, monsters)
+//so passing the same seed back in will rebuild the exact same game.
+//`map` is the name of the map generator new levels are built with. see GENERATOR_NAMES
+pub fn run(seed: u32, map: &str) {
+    let generator = match load_generator(map) {
+        Some(generator) => generator,
+        None => return,
+    };
+    if let Some(monsters) = load_monsters() {
+        println!("seed: {}", seed);
+        play(GameState::new(seed, monsters, with_prefabs(generator, load_prefabs())));
+    }
+}
 
-pub fn run() {
-    
-    let ascii_rendering = true;
-    let mut root = create_root(80,50, ascii_rendering);
-    tcod::system::set_fps(20);
-        
-    let mut tick = 0;
-
-    let mut player = Object::new(0, 0, ascii::player, *tileset::player, "player",true);
-    player.alive = true;
-    //start at a impossible value so we can trigger any sort of "player is at a diffrent location"
-    //logic on the first turn.
-    let mut previous_player_location = (-1,-1); 
-
-    let mut objects = vec!(player);
-    let mut map = Map::create_caves(80,45, &mut objects);
-    //let mut map = Map::new(80,45, Tile::empty());
-    //let (mut map, starting_pos) = Map::create_random_rooms(80,45,&mut objects);
-    //objects[PLAYER].set_pos_tup(starting_pos);
-
-    let mut fov_map = FovMap::new(map.width(), map.height());
-    for y in 0..map.height() {
-        for x in 0..map.width() {
-            let cell = map.at(x,y);
-            fov_map.set(x, y,!cell.block_sight,!cell.blocked);
+//Pick up where the last session left off. If there is no save (or it can't be read)
+//a new game is started with the given seed and map generator instead.
+pub fn run_saved(seed: u32, map: &str) {
+    let path = Path::new(SAVE_FILE);
+    if !path.exists() {
+        return run(seed, map);
+    }
+    let monsters = match load_monsters() {
+        Some(monsters) => monsters,
+        None => return,
+    };
+    let prefabs = load_prefabs();
+
+    match save::load_game(path, monsters.clone(), prefabs.clone()) {
+        Ok(state) => play(state),
+        Err(err) => {
+            let generator = match load_generator(map) {
+                Some(generator) => generator,
+                None => return,
+            };
+            println!("seed: {}", seed);
+            let mut state = GameState::new(seed, monsters, with_prefabs(generator, prefabs));
+            state.messages.add(format!("The old game could not be loaded, {}. Starting a new game instead.", err), colors::RED);
+            play(state);
         }
     }
+}
+
+//There is no game without monsters so a bad data file stops it from starting at all.
+fn load_monsters() -> Option<MonsterTable> {
+    match MonsterTable::load(Path::new(MONSTER_FILE)) {
+        Ok(monsters) => Some(monsters),
+        Err(err) => {
+            println!("{}", err);
+            None
+        }
+    }
+}
+
+//Prefabs are extras, the levels are fine without them.
+fn load_prefabs() -> PrefabLibrary {
+    match PrefabLibrary::load_dir(Path::new(PREFAB_DIR)) {
+        Ok(prefabs) => prefabs,
+        Err(err) => {
+            println!("{}. Playing without prefabs.", err);
+            PrefabLibrary::empty()
+        }
+    }
+}
+
+fn load_generator(name: &str) -> Option<Box<dyn MapGenerator>> {
+    let generator = generator_from_name(name);
+    if generator.is_none() {
+        println!("'{}' is not a map generator. Try one of: {}", name, GENERATOR_NAMES.join(", "));
+    }
+    generator
+}
+
+fn play(mut state: GameState) {
+
+    let mut render_mode = RenderMode::Ascii;
+    let mut root = create_root(SCREEN_WIDTH,SCREEN_HEIGHT, render_mode);
+    tcod::system::set_fps(20);
 
-    let mut con = Offscreen::new(map.width(), map.height());
+    let mut renderer = render_mode.renderer(state.map.width(), state.map.height());
+    //the strip under the map for messages and status
+    let mut panel = Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT);
+    let status = StatusPanel::with_default_widgets();
+
+    //a broken keymap shouldn't stop the game from starting, the defaults still work
+    let keymap = match Keymap::load(Path::new(KEYMAP_FILE)) {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            state.messages.add(format!("{}. Using the default keys.", err), colors::RED);
+            Keymap::with_defaults()
+        }
+    };
 
     //Typically a game loop is considered to be 
     //Get Input, Update Logic, Render
@@ -65,78 +175,41 @@ pub fn run() {
     //we Render first, then Get Input then Update Logical.
     while !root.window_closed(){
 
-        const FOV_ALGO: FovAlgorithm = FovAlgorithm::Basic;
-        const FOV_LIGHT_WALLS: bool = true;
-        const TORCH_RADIUS: i32 = 10;
-
-        //While this should be in the update step
-        //we need this to be true for the first tick.
-        //probably need to stort this out so it can be in the update area.
-        let fov_recompute = {//scope for player lifetime
-            let player = &mut objects[PLAYER];
-            previous_player_location != (player.x, player.y)
-        };
-        //compute the fov before the first tick so the user can see something.
-        //Update fov / explored cells
-        if fov_recompute {
-            let player = &objects[PLAYER];
-            fov_map.compute_fov(player.x, player.y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
-        }
-        for x in 0 .. map.width() {
-            for y in 0 .. map.height() {
-                let cell = map.at_mut(x,y);
-                let (x,y) = (x as i32, y as i32); //convert from index type usize to map type i32
-                let visible = fov_map.is_in_fov(x, y);
-                if visible {
-                    // since it's visible, explore it
-                    cell.explored = true;
+        //Render
+        render_all(&mut root, &mut *renderer, &mut panel, &status, &state);
+
+        //The run is over once the player dies. Leave the last frame up until
+        //a key is pressed and throw the save away so the death sticks.
+        if state.is_game_over() {
+            root.wait_for_keypress(true);
+            if let Err(err) = fs::remove_file(SAVE_FILE) {
+                if err.kind() != io::ErrorKind::NotFound {
+                    println!("could not remove save file: {}", err);
                 }
             }
+            return;
         }
 
-        //Render
-        {
-            con.set_default_foreground(colors::WHITE);
-            con.print(1, 1, tick.to_string());
-
-            render_all(&mut root, &mut con, &objects, &map, ascii_rendering, &fov_map);
-        }
-
-
         //Get input / Update
-        {
-            {//for player borrow scope
-                let player = &mut objects[PLAYER];
-                previous_player_location = (player.x, player.y);
-            }
-            let player_action = handle_keys(&mut root, &mut objects, &map);
-            if player_action == PlayerAction::Exit {
-                break;
-            }
-
-            // let monstars take their turn
-            if objects[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn {
-                for object in &objects {
-                    // only if object is not player
-                    //*const _ is pointer comparison?
-                    if (object as *const _) != (&objects[PLAYER] as *const _) {
-                        
-                        println!("The {} growls!", object.name);
-                    }
-                }
-            }
-
-            if player_action ==  PlayerAction::TookTurn {
-                tick += 1;
-            }
+        let old_render_mode = render_mode;
+        let player_action = handle_keys(&mut root, &keymap, &mut state, &mut render_mode);
+        if render_mode != old_render_mode {
+            //the root was re-created with the other font, the map needs the matching draw info
+            renderer = render_mode.renderer(state.map.width(), state.map.height());
+        }
+        if player_action == PlayerAction::Exit {
+            break;
         }
-        tick += 1;
+    }
 
+    //Both escape and closing the window end up here so either way the game is kept.
+    if let Err(err) = save::save_game(Path::new(SAVE_FILE), &state) {
+        println!("{}", err);
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-enum PlayerAction {
+pub enum PlayerAction {
     TookTurn,
     DidntTakeTurn,
     Exit,
@@ -157,32 +230,77 @@ pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
     })
 }
 
-/// move by the given amount, if the destination is not blocked
-fn move_by(id: usize, dx: i32, dy: i32, map: &Map, objects: &mut [Object]) {
+/// move by the given amount, if the destination is not blocked.
+/// Walking into a tile that changes when bumped (like a closed door) changes it instead of moving,
+/// then the message about it is returned so the caller can show it if the player should see it.
+fn move_by(id: usize, dx: i32, dy: i32, map: &mut Map, objects: &mut [Object]) -> Option<String> {
     let (x, y) = objects[id].pos();
     let (new_x, new_y) = (x + dx, y + dy);
+    if let Some(message) = bump_tile(id, new_x, new_y, map, objects) {
+        return Some(message);
+    }
     if !is_blocked(new_x, new_y, map, objects) {
         objects[id].set_pos(new_x,new_y);
     }
+    None
+}
+
+//Changes the tile at (x, y) if it reacts to being walked into, see TileKind::bumped().
+//Nothing happens while something is standing in the way.
+fn bump_tile(id: usize, x: i32, y: i32, map: &mut Map, objects: &[Object]) -> Option<String> {
+    if !map.in_bounds(x, y) {
+        return None;
+    }
+    let tile = *map.at(x, y);
+    let occupied = objects.iter().any(|object| object.blocks && object.pos() == (x, y));
+    match tile.kind.bumped() {
+        Some((kind, verb)) if tile.blocked && !occupied => {
+            map.set_kind(x, y, kind);
+            Some(format!("{} {} the {}.", objects[id].name, verb, tile.kind.name()))
+        }
+        _ => None,
+    }
 }
 
-fn player_move_or_attack(dx: i32, dy: i32, map: &Map, objects: &mut [Object]) {
+//Step towards the target. If the direct step is blocked try sliding along
+//just one of the axes so monsters can work their way around corners.
+fn move_towards(id: usize, target_x: i32, target_y: i32, map: &mut Map, objects: &mut [Object]) -> Option<String> {
+    let (x, y) = objects[id].pos();
+    let dx = (target_x - x).signum();
+    let dy = (target_y - y).signum();
+
+    let steps = [(dx, dy), (dx, 0), (0, dy)];
+    for &(step_x, step_y) in steps.iter() {
+        let (new_x, new_y) = (x + step_x, y + step_y);
+        //a closed door is blocked but can still be walked into to open it
+        let door = map.in_bounds(new_x, new_y) && map.at(new_x, new_y).blocked && map.at(new_x, new_y).is_passable();
+        if (step_x, step_y) != (0, 0) && (door || !is_blocked(new_x, new_y, map, objects)) {
+            return move_by(id, step_x, step_y, map, objects);
+        }
+    }
+    None
+}
+
+fn player_move_or_attack(dx: i32, dy: i32, map: &mut Map, objects: &mut [Object], messages: &mut Messages) {
     // the coordinates the player is moving to/attacking
     let x = objects[PLAYER].x + dx;
     let y = objects[PLAYER].y + dy;
 
     // try to find an attackable object there
     let target_id = objects.iter().position(|object| {
-        object.pos() == (x, y)
+        object.fighter.is_some() && object.pos() == (x, y)
     });
 
     // attack if target found, move otherwise
     match target_id {
         Some(target_id) => {
-            println!("The {} laughs at your puny efforts to attack him!", objects[target_id].name);
+            let (player, target) = mut_two(PLAYER, target_id, objects);
+            player.attack(target, messages);
         }
         None => {
-            move_by(PLAYER, dx, dy, map, objects);
+            if let Some(message) = move_by(PLAYER, dx, dy, map, objects) {
+                messages.add(message, colors::WHITE);
+            }
         }
     }
 }
@@ -190,11 +308,11 @@ fn player_move_or_attack(dx: i32, dy: i32, map: &Map, objects: &mut [Object]) {
 
 //We need to change which font and some other metadata based on which rendering method we are using
 //OpenGL is needed on my mac. Otherwize it will render a white screen on startup
-fn create_root(width:i32, height:i32, ascii_rendering:bool) -> Root {
+fn create_root(width:i32, height:i32, render_mode: RenderMode) -> Root {
 
-    if ascii_rendering {
+    if render_mode == RenderMode::Ascii {
         let root = Root::initializer()
-            .renderer(Renderer::OpenGL)
+            .renderer(tcod::console::Renderer::OpenGL)
             .size(width,height)
             .title("Rust/libtcod tutorial")
             .font("arial10x10.png", FontLayout::Tcod)
@@ -206,7 +324,7 @@ fn create_root(width:i32, height:i32, ascii_rendering:bool) -> Root {
     }else{
         
         let root = Root::initializer()
-            .renderer(Renderer::OpenGL)
+            .renderer(tcod::console::Renderer::OpenGL)
             .size(width,height)
             .title("Rust/libtcod tutorial")
             .font("TiledFont.png", FontLayout::Tcod)
@@ -230,7 +348,7 @@ fn create_root(width:i32, height:i32, ascii_rendering:bool) -> Root {
     }
 }
 
-fn handle_keys(root: &mut Root, objects: &mut [Object], map: &Map) -> PlayerAction {
+fn handle_keys(root: &mut Root, keymap: &Keymap, state: &mut GameState, render_mode: &mut RenderMode) -> PlayerAction {
     
     //real time
     //I'm thinking if you want animations outside of a turnbased game this is what you 
@@ -244,85 +362,206 @@ fn handle_keys(root: &mut Root, objects: &mut [Object], map: &Map) -> PlayerActi
 
     //turn based
         let key = root.wait_for_keypress(true);
-        let alive = objects[PLAYER].alive;
 
+        let command = match keymap.command_for(key) {
+            Some(command) => command,
+            None => return PlayerAction::DidntTakeTurn,
+        };
 
-        match (key, alive) {
-            (Key{code: Up, ..},true )=>{ 
-                player_move_or_attack(0,-1, map, objects); 
-                return PlayerAction::TookTurn;
-            },
-            (Key{code: Down, ..} ,true) =>{
-                 player_move_or_attack(0,1, map, objects); 
-                 return PlayerAction::TookTurn;
+        //commands that are about the window rather than the game are handled here,
+        //everything else gets turned into an Action for the GameState.
+        if let Some((dx, dy)) = command.direction() {
+            return state.step(Action::Move(dx, dy));
+        }
+        let action = match command {
+            Command::Wait => Action::Wait,
+            Command::Rest => Action::Rest,
+            Command::Descend => Action::Descend,
+            Command::Ascend => Action::Ascend,
+            Command::PickUp => Action::PickUp,
+            Command::MessageLog => {
+                show_message_history(root, &state.messages);
+                return PlayerAction::DidntTakeTurn;
             },
-            (Key{code: Left, ..},true )=>{ 
-                player_move_or_attack(-1,0, map, objects); 
-                return PlayerAction::TookTurn;
+            Command::Inventory => {
+                // show the inventory: if an item is selected, use it
+                match inventory_menu(&state.inventory, "Press the key next to an item to use it, or any other to cancel.\n", root) {
+                    Some(inventory_index) => Action::UseItem(inventory_index),
+                    None => return PlayerAction::DidntTakeTurn,
+                }
             },
-            (Key{code: Right, ..},true) =>{
-                player_move_or_attack(1,0, map, objects); 
-                return PlayerAction::TookTurn;
+            Command::Drop => {
+                // show the inventory; if an item is selected, drop it
+                match inventory_menu(&state.inventory, "Press the key next to an item to drop it, or any other to cancel.\n", root) {
+                    Some(inventory_index) => Action::DropItem(inventory_index),
+                    None => return PlayerAction::DidntTakeTurn,
+                }
             },
-            (Key{code: Enter, alt:true, ..}, .. )=> {
+            Command::ToggleFullscreen => {
                 let currently_fullscreen = root.is_fullscreen();
                 root.set_fullscreen(!currently_fullscreen);
                 return PlayerAction::DidntTakeTurn;
             },
-            (Key{code: Escape, ..}, .. )=> {
+            Command::ToggleRenderMode => {
+                //libtcod only loads a font when the root is initialized, so the root has to be made again.
+                //Root itself is just a marker (everything is drawn again every frame anyway), but libtcod
+                //would open a second window and leak the old console if we init over the top of it.
+                //Deleting the root console first closes the window and frees it, the same tear down and
+                //init libtcod does itself when it switches renderers.
+                let fullscreen = root.is_fullscreen();
+                *render_mode = render_mode.toggled();
+                unsafe {
+                    tcod_sys::TCOD_console_delete(ptr::null_mut());
+                }
+                *root = create_root(SCREEN_WIDTH, SCREEN_HEIGHT, *render_mode);
+                root.set_fullscreen(fullscreen);
+                return PlayerAction::DidntTakeTurn;
+            },
+            Command::Exit => {
                 return PlayerAction::Exit;
             }
-            _ => {}, 
-        }
+            //the moves were already turned into actions above
+            _ => return PlayerAction::DidntTakeTurn,
+        };
 
-        PlayerAction::DidntTakeTurn
+        state.step(action)
 }
 
-fn render_all(root: &mut Root, con: &mut Offscreen, objects: &[Object], map: &Map, ascii_rendering:bool,fov_map: &FovMap){
+//Draws a list of lettered options in the middle of the screen on top of the game
+//and waits for the player to pick one. Returns the index of the picked option.
+fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32, root: &mut Root) -> Option<usize> {
+    assert!(options.len() <= 26, "Cannot have a menu with more than 26 options.");
 
-    //TODO:Maybe instead of branching per interation we can have a method per draw style    
-    for object in objects {
-        if fov_map.is_in_fov(object.x,object.y){
-            if ascii_rendering {
-                con.set_default_foreground(object.ascii.color);
-                con.put_char(object.x, object.y, object.ascii.char, BackgroundFlag::None);
-            }else{
-                con.put_char_ex(object.x,object.y,object.tile.char, object.tile.foreground, object.tile.background);
-            }
+    // calculate total height for the header (after auto-wrap) and one line per option
+    let header_height = root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header);
+    let height = options.len() as i32 + header_height;
+
+    // create an off-screen console that represents the menu's window
+    let mut window = Offscreen::new(width, height);
+
+    // print the header, with auto-wrap
+    window.set_default_foreground(colors::WHITE);
+    window.print_rect_ex(0, 0, width, height, BackgroundFlag::None, TextAlignment::Left, header);
+
+    // print all the options
+    for (index, option_text) in options.iter().enumerate() {
+        let menu_letter = (b'a' + index as u8) as char;
+        let text = format!("({}) {}", menu_letter, option_text.as_ref());
+        window.print_ex(0, header_height + index as i32, BackgroundFlag::None, TextAlignment::Left, text);
+    }
+
+    // blit the contents of "window" to the root console
+    let x = SCREEN_WIDTH / 2 - width / 2;
+    let y = SCREEN_HEIGHT / 2 - height / 2;
+    blit(&window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
+
+    // present the root console to the player and wait for a key-press
+    root.flush();
+    let key = root.wait_for_keypress(true);
+
+    // convert the ASCII code to an index; if it corresponds to an option, return it
+    if key.printable.is_alphabetic() {
+        let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
+        if index < options.len() {
+            Some(index)
+        } else {
+            None
         }
+    } else {
+        None
     }
-    con.set_default_foreground(colors::BLACK);
-
-    for x in 0 .. map.width() {
-        for y in 0 .. map.height() {
-            let cell = map.at(x,y);
-            let wall = cell.is_wall();
-            let (x,y) = (x as i32, y as i32); //convert from index type usize to map type i32
-
-            let visible = fov_map.is_in_fov(x, y);
-            let wall = cell.block_sight;
-            let color = match (visible, wall) {
-                // outside of field of view:
-                (false, true) => COLOR_DARK_WALL,
-                (false, false) => COLOR_DARK_GROUND,
-                // inside fov:
-                (true, true) => COLOR_LIGHT_WALL,
-                (true, false) => COLOR_LIGHT_GROUND,
-            };
+}
 
-            if cell.explored {
-                // show explored tiles only (any visible tile is explored already)
-                con.set_char_background(x, y, color, BackgroundFlag::Set);
+fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root) -> Option<usize> {
+    // show a menu with each item of the inventory as an option
+    let options = if inventory.is_empty() {
+        vec!["Inventory is empty.".into()]
+    } else {
+        inventory.iter().map(|item| {
+            // show additional information, in case it's equipped
+            match item.equipment {
+                Some(equipment) if equipment.equipped => {
+                    format!("{} (on {})", item.name, equipment.slot.name())
+                }
+                _ => item.name.clone()
             }
+        }).collect()
+    };
+
+    let inventory_index = menu(header, &options, INVENTORY_WIDTH, root);
+
+    // if an item was chosen, return it
+    if !inventory.is_empty() {
+        inventory_index
+    } else {
+        None
+    }
+}
+
+fn render_all(root: &mut Root, renderer: &mut dyn Renderer, panel: &mut Offscreen, status: &StatusPanel, state: &GameState){
+    draw_map(renderer, state);
+    renderer.blit_to(root, 0, 0);
+
+    // prepare to render the GUI panel
+    panel.set_default_background(colors::BLACK);
+    panel.clear();
+
+    // the status widgets (hp, xp, depth, ...)
+    status.render(panel, 1, 0, BAR_WIDTH, state);
+
+    // print the game messages, one line at a time, newest at the bottom
+    let mut y = PANEL_HEIGHT;
+    for message in state.messages.iter().rev() {
+        let msg_height = panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, &message.text);
+        y -= msg_height;
+        if y < 0 {
+            break;
         }
+        panel.set_default_foreground(message.color);
+        panel.print_rect(MSG_X, y, MSG_WIDTH, 0, &message.text);
     }
 
-    blit(
-        //from
-        con, (0, 0), (con.width(), con.height()), 
-        //to
-        root, (0, 0), 1.0, 1.0);
+    // blit the contents of `panel` to the root console
+    blit(panel, (0, 0), (SCREEN_WIDTH, PANEL_HEIGHT), root, (0, PANEL_Y), 1.0, 1.0);
     root.flush();
-    
-    con.clear();
+}
+
+//Full screen view of every message with the turn it happened on, newest at the bottom.
+//Up / Down scroll one message, Page Up / Page Down scroll a bunch. Any other key closes it.
+fn show_message_history(root: &mut Root, messages: &Messages) {
+    let mut window = Offscreen::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let max_scroll = messages.len().saturating_sub(1);
+    //how many of the newest messages are scrolled off the bottom
+    let mut scroll = 0;
+
+    loop {
+        window.set_default_background(colors::BLACK);
+        window.clear();
+        window.set_default_foreground(colors::LIGHT_GREY);
+        window.print_ex(SCREEN_WIDTH / 2, 0, BackgroundFlag::None, TextAlignment::Center,
+            "Message history. Arrows / Page Up / Page Down to scroll, any other key to close.");
+
+        let mut y = SCREEN_HEIGHT;
+        for message in messages.iter().rev().skip(scroll) {
+            let text = format!("[turn {}] {}", message.turn, message.text);
+            y -= window.get_height_rect(MSG_X, 0, MSG_WIDTH, 0, &text);
+            //row 0 is the title and row 1 is left empty
+            if y < 2 {
+                break;
+            }
+            window.set_default_foreground(message.color);
+            window.print_rect(MSG_X, y, MSG_WIDTH, 0, &text);
+        }
+
+        blit(&window, (0, 0), (SCREEN_WIDTH, SCREEN_HEIGHT), root, (0, 0), 1.0, 1.0);
+        root.flush();
+
+        match root.wait_for_keypress(true) {
+            Key{code: Up, ..} => scroll = cmp::min(scroll + 1, max_scroll),
+            Key{code: Down, ..} => scroll = scroll.saturating_sub(1),
+            Key{code: PageUp, ..} => scroll = cmp::min(scroll + HISTORY_PAGE, max_scroll),
+            Key{code: PageDown, ..} => scroll = scroll.saturating_sub(HISTORY_PAGE),
+            _ => break,
+        }
+    }
 }
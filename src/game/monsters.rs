@@ -0,0 +1,368 @@
+#![allow(dead_code)]
+
+use std::char;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use tcod::Color;
+use tcod::colors;
+
+use game::object::*;
+use game::draw_info::*;
+use game::ai::*;
+use game::spawn::*;
+
+//Everything needed to create one kind of monster.
+#[derive(Clone, Debug)]
+pub struct MonsterTemplate {
+    pub name: String,
+    pub ascii: AsciiDrawInfo,
+    pub tile: TilesetDrawInfo,
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    pub xp: i32,
+    //looked up with ai_from_name()
+    pub ai: String,
+    //how likely this monster is compared to the others at the same depth.
+    //0 keeps it from showing up at all
+    pub weight: Vec<Step>,
+}
+
+impl MonsterTemplate {
+    pub fn spawn(&self, x: i32, y: i32) -> Object {
+        let mut monster = Object::new(x, y, self.ascii, self.tile, &self.name, true);
+        monster.alive = true;
+        monster.fighter = Some(Fighter::new(self.hp, self.defense, self.power, self.xp, DeathCallback::Monster));
+        monster.ai = ai_from_name(&self.ai);
+        monster
+    }
+}
+
+#[derive(Debug)]
+pub enum MonsterDataError {
+    Io(io::Error),
+    //a line of the file couldn't be understood
+    Parse { line: usize, reason: String },
+    //a monster is missing something it needs (reported at its [name] line)
+    Incomplete { line: usize, name: String, reason: String },
+}
+
+impl fmt::Display for MonsterDataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MonsterDataError::Io(ref err) => write!(f, "could not read monster file: {}", err),
+            MonsterDataError::Parse { line, ref reason } => write!(f, "monster file line {}: {}", line, reason),
+            MonsterDataError::Incomplete { line, ref name, ref reason } => {
+                write!(f, "monster file line {}: monster '{}' {}", line, name, reason)
+            }
+        }
+    }
+}
+
+impl Error for MonsterDataError {}
+
+impl From<io::Error> for MonsterDataError {
+    fn from(err: io::Error) -> Self {
+        MonsterDataError::Io(err)
+    }
+}
+
+//All the monsters the game knows about.
+#[derive(Clone, Debug)]
+pub struct MonsterTable {
+    pub templates: Vec<MonsterTemplate>,
+}
+
+impl MonsterTable {
+    pub fn load(path: &Path) -> Result<MonsterTable, MonsterDataError> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        MonsterTable::parse(&text)
+    }
+
+    //The file is a list of sections, one per monster:
+    //  # comments start with a hash
+    //  [orc]
+    //  glyph = O
+    //  color = 63 127 63
+    //  tile = 259                 (optional, index into the tileset. uses the glyph when missing)
+    //  hp = 10
+    //  defense = 0
+    //  power = 3
+    //  xp = 35
+    //  ai = basic
+    //  weight = 80                (or by depth, "1:80 3:70" is 80 from depth 1 on and 70 from depth 3 on)
+    pub fn parse(text: &str) -> Result<MonsterTable, MonsterDataError> {
+        let mut templates = vec![];
+        let mut current: Option<PartialTemplate> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |reason: String| MonsterDataError::Parse { line: line_number, reason: reason };
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') || line.len() < 3 {
+                    return Err(parse_error("expected '[<monster name>]'".into()));
+                }
+                if let Some(partial) = current.take() {
+                    templates.push(partial.finish()?);
+                }
+                let name = line[1..line.len() - 1].trim();
+                if templates.iter().any(|t: &MonsterTemplate| t.name == name) {
+                    return Err(parse_error(format!("monster '{}' is defined twice", name)));
+                }
+                current = Some(PartialTemplate::new(name, line_number));
+                continue;
+            }
+
+            let partial = match current.as_mut() {
+                Some(partial) => partial,
+                None => return Err(parse_error("expected a '[<monster name>]' before any fields".into())),
+            };
+            let equals = match line.find('=') {
+                Some(equals) => equals,
+                None => return Err(parse_error("expected '<field> = <value>'".into())),
+            };
+            let key = line[..equals].trim();
+            let value = line[equals + 1..].trim();
+            partial.set(key, value).map_err(parse_error)?;
+        }
+
+        if let Some(partial) = current.take() {
+            templates.push(partial.finish()?);
+        }
+        if templates.is_empty() {
+            return Err(MonsterDataError::Parse { line: 0, reason: "no monsters defined".into() });
+        }
+        Ok(MonsterTable { templates: templates })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MonsterTemplate> {
+        self.templates.iter().find(|template| template.name == name)
+    }
+}
+
+//A monster that is still being read in. Fields are filled in as they show up.
+struct PartialTemplate {
+    name: String,
+    line: usize,
+    glyph: Option<char>,
+    color: Option<Color>,
+    tile: Option<char>,
+    hp: Option<i32>,
+    defense: Option<i32>,
+    power: Option<i32>,
+    xp: Option<i32>,
+    ai: Option<String>,
+    weight: Option<Vec<Step>>,
+}
+
+impl PartialTemplate {
+    fn new(name: &str, line: usize) -> Self {
+        PartialTemplate {
+            name: name.into(),
+            line: line,
+            glyph: None,
+            color: None,
+            tile: None,
+            hp: None,
+            defense: None,
+            power: None,
+            xp: None,
+            ai: None,
+            weight: None,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "glyph" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => self.glyph = Some(c),
+                    _ => return Err(format!("glyph must be a single character, got '{}'", value)),
+                }
+            }
+            "color" => self.color = Some(parse_color(value)?),
+            "tile" => {
+                let index: u32 = parse_number("tile", value)?;
+                match char::from_u32(index) {
+                    Some(c) => self.tile = Some(c),
+                    None => return Err(format!("{} is not a valid tile index", index)),
+                }
+            }
+            "hp" => self.hp = Some(parse_number(key, value)?),
+            "defense" => self.defense = Some(parse_number(key, value)?),
+            "power" => self.power = Some(parse_number(key, value)?),
+            "xp" => self.xp = Some(parse_number(key, value)?),
+            "ai" => {
+                if ai_from_name(value).is_none() {
+                    return Err(format!("'{}' is not a known ai", value));
+                }
+                self.ai = Some(value.into());
+            }
+            "weight" => self.weight = Some(parse_steps(value).map_err(|reason| format!("weight: {}", reason))?),
+            _ => return Err(format!("'{}' is not a monster field", key)),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MonsterTemplate, MonsterDataError> {
+        let (name, line) = (self.name, self.line);
+        let missing = |field: &str| MonsterDataError::Incomplete {
+            line: line,
+            name: name.clone(),
+            reason: format!("is missing '{}'", field),
+        };
+
+        let glyph = self.glyph.ok_or_else(|| missing("glyph"))?;
+        let color = self.color.ok_or_else(|| missing("color"))?;
+        let hp = self.hp.ok_or_else(|| missing("hp"))?;
+        if hp <= 0 {
+            return Err(MonsterDataError::Incomplete { line: line, name: name.clone(), reason: "needs more than 0 hp".into() });
+        }
+
+        let template = MonsterTemplate {
+            ascii: AsciiDrawInfo { char: glyph, color: color },
+            //without a tile of its own it looks the same in both modes
+            tile: match self.tile {
+                Some(tile) => TilesetDrawInfo::new(tile),
+                None => TilesetDrawInfo { char: glyph, foreground: color, background: colors::BLACK },
+            },
+            hp: hp,
+            defense: self.defense.ok_or_else(|| missing("defense"))?,
+            power: self.power.ok_or_else(|| missing("power"))?,
+            xp: self.xp.ok_or_else(|| missing("xp"))?,
+            ai: self.ai.ok_or_else(|| missing("ai"))?,
+            weight: self.weight.ok_or_else(|| missing("weight"))?,
+            name: name,
+        };
+        Ok(template)
+    }
+}
+
+fn parse_number<T: FromStr>(field: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} must be a number, got '{}'", field, value))
+}
+
+//"r g b"
+fn parse_color(value: &str) -> Result<Color, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 3 {
+        return Err(format!("color must be 'r g b', got '{}'", value));
+    }
+    Ok(Color {
+        r: parse_number("color", parts[0])?,
+        g: parse_number("color", parts[1])?,
+        b: parse_number("color", parts[2])?,
+    })
+}
+
+//The monsters in the data file the game ships with, for the tests.
+#[cfg(test)]
+impl MonsterTable {
+    pub fn shipped() -> MonsterTable {
+        MonsterTable::parse(include_str!("../../data/monsters.txt")).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //line 1 is [orc], then one field per line in this order
+    const ORC: &'static str = "[orc]
+glyph = O
+color = 63 127 63
+hp = 10
+defense = 0
+power = 3
+xp = 35
+ai = basic
+weight = 80
+";
+
+    //the line of a Parse error
+    fn parse_error_line(text: &str) -> usize {
+        match MonsterTable::parse(text) {
+            Err(MonsterDataError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other.map(|table| table.templates.len())),
+        }
+    }
+
+    //the line and reason of an Incomplete error
+    fn incomplete(text: &str) -> (usize, String) {
+        match MonsterTable::parse(text) {
+            Err(MonsterDataError::Incomplete { line, name, reason }) => {
+                assert_eq!(name, "orc");
+                (line, reason)
+            }
+            other => panic!("expected an incomplete monster, got {:?}", other.map(|table| table.templates.len())),
+        }
+    }
+
+    #[test]
+    fn parses_the_monster_file() {
+        let table = MonsterTable::parse(include_str!("../../data/monsters.txt")).unwrap();
+        let orc = table.get("orc").unwrap();
+        assert_eq!(orc.ascii.char, 'O');
+        assert_eq!(orc.hp, 10);
+        assert_eq!(orc.ai, "basic");
+        assert!(table.get("troll").is_some());
+        assert!(table.get("dragon").is_none());
+    }
+
+    #[test]
+    fn duplicate_monsters() {
+        assert_eq!(parse_error_line(&format!("{}{}", ORC, ORC)), 10);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        assert_eq!(parse_error_line(&ORC.replace("xp = 35", "speed = 3")), 7);
+    }
+
+    #[test]
+    fn bad_colors() {
+        assert_eq!(parse_error_line(&ORC.replace("63 127 63", "63 127")), 3);
+        assert_eq!(parse_error_line(&ORC.replace("63 127 63", "red green blue")), 3);
+    }
+
+    #[test]
+    fn glyphs_are_one_char() {
+        assert_eq!(parse_error_line(&ORC.replace("glyph = O", "glyph = Oo")), 2);
+    }
+
+    #[test]
+    fn unknown_ai() {
+        assert_eq!(parse_error_line(&ORC.replace("ai = basic", "ai = sleepy")), 8);
+    }
+
+    #[test]
+    fn fields_before_any_monster() {
+        assert_eq!(parse_error_line(&format!("hp = 3\n{}", ORC)), 1);
+    }
+
+    #[test]
+    fn missing_hp() {
+        let (line, reason) = incomplete(&ORC.replace("hp = 10\n", ""));
+        assert_eq!(line, 1);
+        assert!(reason.contains("hp"));
+    }
+
+    #[test]
+    fn zero_hp() {
+        let (line, reason) = incomplete(&ORC.replace("hp = 10", "hp = 0"));
+        assert_eq!(line, 1);
+        assert!(reason.contains("hp"));
+    }
+}
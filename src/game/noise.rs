@@ -0,0 +1,81 @@
+#![allow(dead_code)]
+
+use rand::Rng;
+
+use game::rng::GameRng;
+
+//Plain 2d Perlin noise, based on Ken Perlin's "improved noise".
+//https://mrl.nyu.edu/~perlin/noise/
+//The permutation table is shuffled with the game rng so the same seed gives the same noise.
+pub struct Perlin {
+    //0..256 shuffled, then repeated once so lookups can go past 255 without wrapping
+    perm: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(rng: &mut GameRng) -> Self {
+        let mut perm: Vec<usize> = (0 .. 256).collect();
+        rng.shuffle(&mut perm);
+        let repeated = perm.clone();
+        perm.extend(repeated);
+        Perlin { perm: perm }
+    }
+
+    //Smooth noise around -1.0 to 1.0. Whole numbers always come out as 0
+    //so scale the coordinates down to get anything interesting.
+    pub fn noise(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        //which cell of the grid the point is in, and where inside of it
+        let (cell_x, cell_y) = ((x0 as i32 & 255) as usize, (y0 as i32 & 255) as usize);
+        let (dx, dy) = (x - x0, y - y0);
+
+        let hash = |cx: usize, cy: usize| self.perm[self.perm[cx] + cy];
+        let n00 = gradient(hash(cell_x, cell_y), dx, dy);
+        let n10 = gradient(hash(cell_x + 1, cell_y), dx - 1.0, dy);
+        let n01 = gradient(hash(cell_x, cell_y + 1), dx, dy - 1.0);
+        let n11 = gradient(hash(cell_x + 1, cell_y + 1), dx - 1.0, dy - 1.0);
+
+        let (u, v) = (fade(dx), fade(dy));
+        lerp(v, lerp(u, n00, n10), lerp(u, n01, n11))
+    }
+
+    //A few layers of noise on top of each other, each one twice as detailed and
+    //`persistence` times as strong as the last. Gives rougher, more natural looking shapes.
+    //Still roughly -1.0 to 1.0.
+    pub fn fractal(&self, x: f32, y: f32, octaves: u32, persistence: f32) -> f32 {
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut max_value = 0.0;
+        for _ in 0 .. octaves {
+            total += self.noise(x * frequency, y * frequency) * amplitude;
+            max_value += amplitude;
+            amplitude *= persistence;
+            frequency *= 2.0;
+        }
+        total / max_value
+    }
+}
+
+//dot product of (x, y) with one of 8 gradient directions picked by the hash
+fn gradient(hash: usize, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+//6t^5 - 15t^4 + 10t^3, eases in and out so the cell edges don't show
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
@@ -4,7 +4,11 @@ use tcod::colors;
 
 use tcod::console::*;
 
+use std::cmp;
+
 use game::*;
+use game::ai::*;
+use game::messages::*;
 
 
 #[derive(Debug)]
@@ -16,6 +20,13 @@ pub struct Object{
     pub name:String,
     pub blocks:bool,
     pub alive:bool,
+    //drawn outside of the fov once its tile has been explored (stairs and other landmarks)
+    pub always_visible:bool,
+    pub stairs:Option<Stairs>,
+    pub fighter:Option<Fighter>,
+    pub ai:Option<Box<dyn Ai>>,
+    pub item:Option<Item>,
+    pub equipment:Option<Equipment>,
 }
 
 impl Object{
@@ -28,6 +39,12 @@ impl Object{
             name:name.into(),
             blocks:blocks,
             alive:false,
+            always_visible:false,
+            stairs:None,
+            fighter:None,
+            ai:None,
+            item:None,
+            equipment:None,
         }
     }
 
@@ -44,6 +61,57 @@ impl Object{
     }
 
 
+    /// return the distance to another object
+    pub fn distance_to(&self, other: &Object) -> f32 {
+        let dx = other.x - self.x;
+        let dy = other.y - self.y;
+        ((dx.pow(2) + dy.pow(2)) as f32).sqrt()
+    }
+
+    //returns the xp the object was worth if this killed it
+    pub fn take_damage(&mut self, damage: i32, messages: &mut Messages) -> Option<i32> {
+        // apply damage if possible
+        if let Some(fighter) = self.fighter.as_mut() {
+            if damage > 0 {
+                fighter.hp -= damage;
+            }
+        }
+
+        // check for death, call the death function
+        if let Some(fighter) = self.fighter {
+            if fighter.hp <= 0 {
+                self.alive = false;
+                fighter.on_death.callback(self, messages);
+                return Some(fighter.xp);
+            }
+        }
+        None
+    }
+
+    /// heal by the given amount, without going over the maximum
+    pub fn heal(&mut self, amount: i32) {
+        if let Some(ref mut fighter) = self.fighter {
+            fighter.hp = cmp::min(fighter.hp + amount, fighter.max_hp);
+        }
+    }
+
+    pub fn attack(&mut self, target: &mut Object, messages: &mut Messages) {
+        // a simple formula for attack damage
+        let damage = self.fighter.map_or(0, |f| f.power) - target.fighter.map_or(0, |f| f.defense);
+        if damage > 0 {
+            // make the target take some damage
+            messages.add(format!("{} attacks {} for {} hit points.", self.name, target.name, damage), colors::WHITE);
+            if let Some(xp) = target.take_damage(damage, messages) {
+                // yield experience to the attacker
+                if let Some(ref mut fighter) = self.fighter {
+                    fighter.xp += xp;
+                }
+            }
+        } else {
+            messages.add(format!("{} attacks {} but it has no effect!", self.name, target.name), colors::WHITE);
+        }
+    }
+
     pub fn move_by(&mut self,map:&Map, dx: i32, dy: i32) {
         let new_x = self.x + dx;
         let new_y = self.y + dy;
@@ -56,4 +124,121 @@ impl Object{
             self.y = new_y;   
         }
     }
-}
\ No newline at end of file
+}
+
+//What happens when an item is used from the inventory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Item {
+    Heal,
+    Lightning,
+    //anything that gets equipped. The bonuses are in the object's Equipment
+    Equipment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Slot {
+    RightHand,
+    LeftHand,
+}
+
+impl Slot {
+    pub fn name(self) -> &'static str {
+        match self {
+            Slot::RightHand => "right hand",
+            Slot::LeftHand => "left hand",
+        }
+    }
+}
+
+//An item that can be worn. Only one item can be equipped per slot.
+//While equipped the bonuses are added straight onto the wearer's Fighter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Equipment {
+    pub slot: Slot,
+    pub equipped: bool,
+    pub power_bonus: i32,
+    pub defense_bonus: i32,
+}
+
+//Which way a staircase goes. Down leads to depth + 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stairs {
+    Down,
+    Up,
+}
+
+// combat-related properties and methods (monster, player, NPC).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fighter {
+    pub max_hp: i32,
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    //for monsters the xp they give when killed, for the player the xp earned so far
+    pub xp: i32,
+    pub on_death: DeathCallback,
+}
+
+impl Fighter {
+    pub fn new(hp: i32, defense: i32, power: i32, xp: i32, on_death: DeathCallback) -> Self {
+        Fighter {
+            max_hp: hp,
+            hp: hp,
+            defense: defense,
+            power: power,
+            xp: xp,
+            on_death: on_death,
+        }
+    }
+}
+
+//What happens to an object once its hp runs out.
+//An enum instead of a function pointer so it can be compared, printed and saved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeathCallback {
+    Player,
+    Monster,
+}
+
+impl DeathCallback {
+    fn callback(self, object: &mut Object, messages: &mut Messages) {
+        match self {
+            DeathCallback::Player => player_death(object, messages),
+            DeathCallback::Monster => monster_death(object, messages),
+        }
+    }
+}
+
+fn player_death(player: &mut Object, messages: &mut Messages) {
+    // the game ended!
+    messages.add("You died!", colors::RED);
+
+    // for added effect, transform the player into a corpse!
+    player.ascii = ascii::corpse;
+    player.tile = *tileset::corpse;
+}
+
+fn monster_death(monster: &mut Object, messages: &mut Messages) {
+    // transform it into a nasty corpse! it doesn't block, can't be
+    // attacked and doesn't move
+    messages.add(format!("{} is dead!", monster.name), colors::ORANGE);
+    monster.ascii = ascii::corpse;
+    monster.tile = *tileset::corpse;
+    monster.blocks = false;
+    monster.fighter = None;
+    monster.ai = None;
+    monster.name = format!("remains of {}", monster.name);
+}
+
+/// Mutably borrow two *separate* elements from the given slice.
+/// Panics when the indexes are equal or out of bounds.
+pub fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut T, &mut T) {
+    assert!(first_index != second_index);
+    let split_at_index = cmp::max(first_index, second_index);
+    let (first_slice, second_slice) = items.split_at_mut(split_at_index);
+    if first_index < second_index {
+        (&mut first_slice[first_index], &mut second_slice[0])
+    } else {
+        (&mut second_slice[0], &mut first_slice[second_index])
+    }
+}
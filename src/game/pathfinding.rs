@@ -0,0 +1,460 @@
+#![allow(dead_code)]
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use game::object::*;
+use game::terrain::*;
+
+//Step costs are scaled by 10 so diagonal steps can cost ~sqrt(2) without floats.
+const ORTHOGONAL_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+//Going through a closed door takes an extra turn to open it first.
+const OPEN_COST: u32 = ORTHOGONAL_COST;
+
+const ORTHOGONAL_STEPS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const ALL_STEPS: [(i32, i32); 8] = [(0, -1), (1, 0), (0, 1), (-1, 0), (1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Movement {
+    //only up, down, left and right
+    Orthogonal,
+    //the 4 orthogonal directions plus the diagonals
+    Diagonal,
+}
+
+//How objects that block (monsters, the player) are treated while searching.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Obstacles {
+    //only the map matters
+    Ignore,
+    //treat them like walls
+    Block,
+    //walkable but each one adds this much to the cost (in the same units as a step, 10 per tile)
+    Cost(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathOptions {
+    pub movement: Movement,
+    pub obstacles: Obstacles,
+}
+
+impl PathOptions {
+    pub fn new(movement: Movement, obstacles: Obstacles) -> Self {
+        PathOptions { movement: movement, obstacles: obstacles }
+    }
+}
+
+//The map flattened into per cell step costs so the searches don't have to
+//look through the objects list for every tile they visit.
+struct CostGrid {
+    width: i32,
+    height: i32,
+    //extra cost of entering each cell (tile cost + opening it + obstacles). None means the cell can't be entered
+    costs: Vec<Option<u32>>,
+    movement: Movement,
+}
+
+impl CostGrid {
+    fn new<T: Terrain>(terrain: &T, objects: &[Object], options: PathOptions) -> Self {
+        let mut costs: Vec<Option<u32>> = Vec::with_capacity((terrain.width() * terrain.height()) as usize);
+        for y in 0 .. terrain.height() {
+            for x in 0 .. terrain.width() {
+                costs.push(if terrain.is_walkable(x, y) {
+                    Some(terrain.move_cost(x, y))
+                } else if terrain.is_openable(x, y) {
+                    Some(OPEN_COST + terrain.move_cost(x, y))
+                } else {
+                    None
+                });
+            }
+        }
+
+        let mut grid = CostGrid { width: terrain.width(), height: terrain.height(), costs: costs, movement: options.movement };
+        for object in objects.iter().filter(|object| object.blocks && terrain.in_bounds(object.x, object.y)) {
+            let index = grid.index(object.x, object.y);
+            grid.costs[index] = match (options.obstacles, grid.costs[index]) {
+                (Obstacles::Ignore, cost) => cost,
+                (Obstacles::Block, _) => None,
+                (Obstacles::Cost(extra), Some(cost)) => Some(cost + extra),
+                (Obstacles::Cost(_), None) => None,
+            };
+        }
+        grid
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn steps(&self) -> &'static [(i32, i32)] {
+        match self.movement {
+            Movement::Orthogonal => &ORTHOGONAL_STEPS,
+            Movement::Diagonal => &ALL_STEPS,
+        }
+    }
+
+    //the cost of stepping into (x, y) or None if it can't be entered.
+    fn enter_cost(&self, x: i32, y: i32, dx: i32, dy: i32) -> Option<u32> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        let step = if dx != 0 && dy != 0 { DIAGONAL_COST } else { ORTHOGONAL_COST };
+        self.costs[self.index(x, y)].map(|extra| step + extra)
+    }
+
+    //a guess of the remaining cost that never overestimates so A* stays optimal
+    fn heuristic(&self, from: (i32, i32), to: (i32, i32)) -> u32 {
+        let dx = (from.0 - to.0).abs() as u32;
+        let dy = (from.1 - to.1).abs() as u32;
+        match self.movement {
+            Movement::Orthogonal => (dx + dy) * ORTHOGONAL_COST,
+            Movement::Diagonal => {
+                let (low, high) = if dx < dy { (dx, dy) } else { (dy, dx) };
+                low * DIAGONAL_COST + (high - low) * ORTHOGONAL_COST
+            }
+        }
+    }
+}
+
+/// Find the cheapest path between two points.
+/// The returned path doesn't include `from` but does include `to`.
+/// `to` is always allowed to be occupied by an object, so monsters can path to the player.
+/// Cells that can be opened (closed doors) are part of paths, the step into them opens them.
+pub fn astar<T: Terrain>(terrain: &T, objects: &[Object], from: (i32, i32), to: (i32, i32), options: PathOptions) -> Option<Vec<(i32, i32)>> {
+    if !terrain.in_bounds(from.0, from.1) || !terrain.is_walkable(to.0, to.1) {
+        return None;
+    }
+    if from == to {
+        return Some(vec![]);
+    }
+
+    let mut grid = CostGrid::new(terrain, objects, options);
+    let goal_index = grid.index(to.0, to.1);
+    grid.costs[goal_index] = Some(0);
+
+    let size = (grid.width * grid.height) as usize;
+    let mut best_cost = vec![u32::max_value(); size];
+    let mut came_from: Vec<Option<usize>> = vec![None; size];
+    let mut open = BinaryHeap::new();
+
+    let start_index = grid.index(from.0, from.1);
+    best_cost[start_index] = 0;
+    open.push(Reverse((grid.heuristic(from, to), 0, from)));
+
+    while let Some(Reverse((_, cost, (x, y)))) = open.pop() {
+        let index = grid.index(x, y);
+        if index == goal_index {
+            break;
+        }
+        //a cheaper way to this cell was already found after this entry was queued
+        if cost > best_cost[index] {
+            continue;
+        }
+
+        for &(dx, dy) in grid.steps() {
+            let (next_x, next_y) = (x + dx, y + dy);
+            let step_cost = match grid.enter_cost(next_x, next_y, dx, dy) {
+                Some(step_cost) => step_cost,
+                None => continue,
+            };
+            let next_index = grid.index(next_x, next_y);
+            let next_cost = cost + step_cost;
+            if next_cost < best_cost[next_index] {
+                best_cost[next_index] = next_cost;
+                came_from[next_index] = Some(index);
+                let estimate = next_cost + grid.heuristic((next_x, next_y), to);
+                open.push(Reverse((estimate, next_cost, (next_x, next_y))));
+            }
+        }
+    }
+
+    if best_cost[goal_index] == u32::max_value() {
+        return None;
+    }
+
+    //walk backwards from the goal to rebuild the path
+    let mut path = vec![];
+    let mut index = goal_index;
+    while index != start_index {
+        path.push((index as i32 % grid.width, index as i32 / grid.width));
+        index = came_from[index].expect("every visited cell has a parent");
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Distance from every cell to the nearest goal.
+/// Useful when a lot of things need to get to the same place (e.g. everything chasing the player)
+/// or when something needs to get away from it (walk uphill).
+pub struct DijkstraMap {
+    width: i32,
+    height: i32,
+    movement: Movement,
+    distances: Vec<Option<u32>>,
+}
+
+impl DijkstraMap {
+    pub fn new<T: Terrain>(terrain: &T, objects: &[Object], goals: &[(i32, i32)], options: PathOptions) -> Self {
+        let mut grid = CostGrid::new(terrain, objects, options);
+        let size = (grid.width * grid.height) as usize;
+        let mut distances: Vec<Option<u32>> = vec![None; size];
+        let mut open = BinaryHeap::new();
+
+        for &(x, y) in goals.iter().filter(|&&(x, y)| terrain.is_walkable(x, y)) {
+            let index = grid.index(x, y);
+            //goals are reachable even if something is standing on them
+            grid.costs[index] = Some(0);
+            distances[index] = Some(0);
+            open.push(Reverse((0, (x, y))));
+        }
+
+        while let Some(Reverse((cost, (x, y)))) = open.pop() {
+            if distances[grid.index(x, y)].map_or(false, |best| cost > best) {
+                continue;
+            }
+            for &(dx, dy) in grid.steps() {
+                let (next_x, next_y) = (x + dx, y + dy);
+                let step_cost = match grid.enter_cost(next_x, next_y, dx, dy) {
+                    Some(step_cost) => step_cost,
+                    None => continue,
+                };
+                let next_index = grid.index(next_x, next_y);
+                let next_cost = cost + step_cost;
+                if distances[next_index].map_or(true, |best| next_cost < best) {
+                    distances[next_index] = Some(next_cost);
+                    open.push(Reverse((next_cost, (next_x, next_y))));
+                }
+            }
+        }
+
+        DijkstraMap { width: grid.width, height: grid.height, movement: options.movement, distances: distances }
+    }
+
+    /// None if the cell can't reach any goal.
+    pub fn distance(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        self.distances[(y * self.width + x) as usize]
+    }
+
+    /// The neighbour of (x, y) that is closest to a goal, or None if there is nowhere better to go.
+    pub fn next_step(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let steps: &[(i32, i32)] = match self.movement {
+            Movement::Orthogonal => &ORTHOGONAL_STEPS,
+            Movement::Diagonal => &ALL_STEPS,
+        };
+        let mut best = self.distance(x, y);
+        let mut best_step = None;
+        for &(dx, dy) in steps {
+            if let Some(distance) = self.distance(x + dx, y + dy) {
+                if best.map_or(true, |best| distance < best) {
+                    best = Some(distance);
+                    best_step = Some((x + dx, y + dy));
+                }
+            }
+        }
+        best_step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::map::*;
+    use game::tile::*;
+    use game::draw_info::*;
+
+    fn blocker(x: i32, y: i32) -> Object {
+        Object::new(x, y, ascii::player, *tileset::player, "blocker", true)
+    }
+
+    const DIAGONAL: PathOptions = PathOptions { movement: Movement::Diagonal, obstacles: Obstacles::Ignore };
+    const ORTHOGONAL: PathOptions = PathOptions { movement: Movement::Orthogonal, obstacles: Obstacles::Ignore };
+
+    #[test]
+    fn straight_line() {
+        let map = Map::from_rows(&["....."]);
+        let path = astar(&map, &[], (0, 0), (4, 0), DIAGONAL).unwrap();
+        assert_eq!(path, vec![(1, 0), (2, 0), (3, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn same_start_and_goal() {
+        let map = Map::from_rows(&["..."]);
+        assert_eq!(astar(&map, &[], (1, 0), (1, 0), DIAGONAL), Some(vec![]));
+    }
+
+    #[test]
+    fn walks_around_walls() {
+        let map = Map::from_rows(&[
+            ".#...",
+            ".#.#.",
+            "...#.",
+        ]);
+        let path = astar(&map, &[], (0, 0), (4, 0), ORTHOGONAL).unwrap();
+        assert_eq!(path.last(), Some(&(4, 0)));
+        assert!(path.iter().all(|&(x, y)| !map.at(x, y).blocked));
+        assert_eq!(path.len(), 8);
+    }
+
+    #[test]
+    fn diagonal_paths_are_shorter() {
+        let map = Map::from_rows(&[
+            "....",
+            "....",
+            "....",
+            "....",
+        ]);
+        assert_eq!(astar(&map, &[], (0, 0), (3, 3), DIAGONAL).unwrap().len(), 3);
+        assert_eq!(astar(&map, &[], (0, 0), (3, 3), ORTHOGONAL).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn no_path_through_walls() {
+        let map = Map::from_rows(&[
+            "..#..",
+            "..#..",
+            "..#..",
+        ]);
+        assert_eq!(astar(&map, &[], (0, 0), (4, 0), DIAGONAL), None);
+        assert_eq!(astar(&map, &[], (0, 0), (2, 0), DIAGONAL), None);
+    }
+
+    #[test]
+    fn blocking_objects() {
+        let map = Map::from_rows(&[
+            "...",
+            "#.#",
+            "...",
+        ]);
+        let objects = vec![blocker(1, 1)];
+
+        let ignore = astar(&map, &objects, (1, 0), (1, 2), DIAGONAL);
+        assert_eq!(ignore, Some(vec![(1, 1), (1, 2)]));
+
+        let block = PathOptions::new(Movement::Diagonal, Obstacles::Block);
+        assert_eq!(astar(&map, &objects, (1, 0), (1, 2), block), None);
+
+        let cost = PathOptions::new(Movement::Diagonal, Obstacles::Cost(50));
+        assert_eq!(astar(&map, &objects, (1, 0), (1, 2), cost), Some(vec![(1, 1), (1, 2)]));
+    }
+
+    #[test]
+    fn object_costs_make_detours() {
+        let map = Map::from_rows(&[
+            ".....",
+            ".###.",
+            ".....",
+        ]);
+        let objects = vec![blocker(1, 0)];
+        let cost = PathOptions::new(Movement::Orthogonal, Obstacles::Cost(100));
+        let path = astar(&map, &objects, (0, 0), (2, 0), cost).unwrap();
+        assert!(!path.contains(&(1, 0)));
+    }
+
+    #[test]
+    fn goal_can_be_occupied() {
+        let map = Map::from_rows(&["..."]);
+        let objects = vec![blocker(2, 0)];
+        let block = PathOptions::new(Movement::Diagonal, Obstacles::Block);
+        assert_eq!(astar(&map, &objects, (0, 0), (2, 0), block), Some(vec![(1, 0), (2, 0)]));
+    }
+
+    #[test]
+    fn paths_go_through_closed_doors() {
+        let mut map = Map::from_rows(&[
+            "..#..",
+            "..#..",
+            "..#..",
+        ]);
+        map.set(2, 1, Tile::new(TileKind::ClosedDoor));
+        let path = astar(&map, &[], (0, 1), (4, 1), ORTHOGONAL).unwrap();
+        assert_eq!(path, vec![(1, 1), (2, 1), (3, 1), (4, 1)]);
+
+        let mut terrain = TerrainGrid::new(&map);
+        assert_eq!(astar(&terrain, &[], (0, 1), (4, 1), ORTHOGONAL), Some(path));
+
+        map.set(2, 1, Tile::wall());
+        terrain.sync(&mut map);
+        assert_eq!(astar(&terrain, &[], (0, 1), (4, 1), ORTHOGONAL), None);
+    }
+
+    #[test]
+    fn costly_tiles_make_detours() {
+        let mut map = Map::from_rows(&[
+            ".....",
+            ".....",
+            ".....",
+        ]);
+        let mut terrain = TerrainGrid::new(&map);
+        let straight = vec![(1, 1), (2, 1), (3, 1), (4, 1)];
+        assert_eq!(astar(&terrain, &[], (0, 1), (4, 1), ORTHOGONAL), Some(straight.clone()));
+
+        //one step around costs 20 more, wading through costs 10 more
+        map.set(2, 1, Tile::new(TileKind::ShallowWater));
+        assert!(terrain.sync(&mut map));
+        assert_eq!(astar(&terrain, &[], (0, 1), (4, 1), ORTHOGONAL), Some(straight));
+
+        //lava is worth going around
+        map.set(2, 1, Tile::new(TileKind::Lava));
+        assert!(terrain.sync(&mut map));
+        let path = astar(&terrain, &[], (0, 1), (4, 1), ORTHOGONAL).unwrap();
+        assert!(!path.contains(&(2, 1)));
+        assert_eq!(path.len(), 6);
+
+        //unless there is no other way
+        map.set(2, 0, Tile::wall());
+        map.set(2, 2, Tile::wall());
+        terrain.sync(&mut map);
+        let path = astar(&terrain, &[], (0, 1), (4, 1), ORTHOGONAL).unwrap();
+        assert!(path.contains(&(2, 1)));
+    }
+
+    #[test]
+    fn dijkstra_distances() {
+        let map = Map::from_rows(&[
+            "...",
+            ".#.",
+            "...",
+        ]);
+        let dijkstra = DijkstraMap::new(&map, &[], &[(0, 0)], ORTHOGONAL);
+        assert_eq!(dijkstra.distance(0, 0), Some(0));
+        assert_eq!(dijkstra.distance(2, 0), Some(2 * ORTHOGONAL_COST));
+        assert_eq!(dijkstra.distance(2, 2), Some(4 * ORTHOGONAL_COST));
+        assert_eq!(dijkstra.distance(1, 1), None);
+        assert_eq!(dijkstra.distance(-1, 0), None);
+    }
+
+    #[test]
+    fn dijkstra_next_step_goes_downhill() {
+        let map = Map::from_rows(&[
+            ".....",
+            ".###.",
+            ".....",
+        ]);
+        let dijkstra = DijkstraMap::new(&map, &[], &[(4, 2)], ORTHOGONAL);
+        let mut position = (0, 0);
+        let mut steps = 0;
+        while let Some(next) = dijkstra.next_step(position.0, position.1) {
+            position = next;
+            steps += 1;
+        }
+        assert_eq!(position, (4, 2));
+        assert_eq!(steps, 6);
+    }
+
+    #[test]
+    fn dijkstra_with_several_goals() {
+        let map = Map::from_rows(&["......"]);
+        let dijkstra = DijkstraMap::new(&map, &[], &[(0, 0), (5, 0)], ORTHOGONAL);
+        assert_eq!(dijkstra.distance(1, 0), Some(ORTHOGONAL_COST));
+        assert_eq!(dijkstra.distance(4, 0), Some(ORTHOGONAL_COST));
+        assert_eq!(dijkstra.distance(2, 0), Some(2 * ORTHOGONAL_COST));
+    }
+}
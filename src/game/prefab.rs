@@ -0,0 +1,395 @@
+#![allow(dead_code)]
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use rand::Rng;
+
+use game::rect::*;
+use game::tile::*;
+use game::mapgen::*;
+use game::rng::GameRng;
+
+//How many prefabs every level tries to get, and how many spots are tried for each one
+//before giving up on it.
+const PREFABS_PER_LEVEL: i32 = 2;
+const ATTEMPTS_PER_PREFAB: i32 = 100;
+
+//One cell of a prefab.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrefabCell {
+    //leave whatever the generator put there
+    Keep,
+    Tile(TileKind),
+    //floor that always gets a monster / item
+    Monster,
+    Item,
+}
+
+impl PrefabCell {
+    fn from_char(c: char) -> Option<PrefabCell> {
+        match c {
+            ' ' => Some(PrefabCell::Keep),
+            'M' => Some(PrefabCell::Monster),
+            'I' => Some(PrefabCell::Item),
+            _ => TileKind::from_char(c).map(PrefabCell::Tile),
+        }
+    }
+
+    fn tile(self) -> Option<Tile> {
+        match self {
+            PrefabCell::Keep => None,
+            PrefabCell::Tile(kind) => Some(Tile::new(kind)),
+            PrefabCell::Monster | PrefabCell::Item => Some(Tile::empty()),
+        }
+    }
+}
+
+//A hand made piece of map that gets stamped on top of generated levels.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    cells: Vec<PrefabCell>,
+}
+
+impl Prefab {
+    //The prefab is drawn with one char per cell:
+    //  M monster   I item   (space) leave the map as it is
+    //  anything else is a tile in the same chars as the save file, see TileKind::to_char()
+    //  (# wall   . floor   + door   ~ deep water   & lava ...)
+    //Lines starting with // are comments. Short rows are filled up with spaces.
+    pub fn parse(name: &str, text: &str) -> Result<Prefab, PrefabError> {
+        let mut rows: Vec<Vec<PrefabCell>> = vec![];
+        for (index, line) in text.lines().enumerate() {
+            if line.trim_start().starts_with("//") {
+                continue;
+            }
+            let mut row = vec![];
+            for c in line.trim_end().chars() {
+                match PrefabCell::from_char(c) {
+                    Some(cell) => row.push(cell),
+                    None => return Err(PrefabError::Parse {
+                        name: name.into(),
+                        line: index + 1,
+                        reason: format!("'{}' is not a prefab cell", c),
+                    }),
+                }
+            }
+            rows.push(row);
+        }
+
+        //blank lines around the drawing don't count
+        while rows.last().map_or(false, |row| row.is_empty()) {
+            rows.pop();
+        }
+        while rows.first().map_or(false, |row| row.is_empty()) {
+            rows.remove(0);
+        }
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if width == 0 {
+            return Err(PrefabError::Parse { name: name.into(), line: 0, reason: "the prefab is empty".into() });
+        }
+
+        let mut cells = vec![];
+        for mut row in rows.iter().cloned() {
+            row.resize(width, PrefabCell::Keep);
+            cells.extend(row);
+        }
+        Ok(Prefab {
+            name: name.into(),
+            width: width as i32,
+            height: rows.len() as i32,
+            cells: cells,
+        })
+    }
+
+    pub fn at(&self, x: i32, y: i32) -> PrefabCell {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    //Turned a quarter clockwise.
+    pub fn rotated(&self) -> Prefab {
+        let (width, height) = (self.height, self.width);
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0 .. height {
+            for x in 0 .. width {
+                cells.push(self.at(y, self.height - 1 - x));
+            }
+        }
+        Prefab { name: self.name.clone(), width: width, height: height, cells: cells }
+    }
+
+    //Flipped left to right.
+    pub fn mirrored(&self) -> Prefab {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0 .. self.height {
+            for x in 0 .. self.width {
+                cells.push(self.at(self.width - 1 - x, y));
+            }
+        }
+        Prefab { name: self.name.clone(), width: self.width, height: self.height, cells: cells }
+    }
+
+    //Any of the 8 ways the prefab can be turned / flipped.
+    pub fn random_orientation(&self, rng: &mut GameRng) -> Prefab {
+        let mut prefab = if rng.gen() { self.mirrored() } else { self.clone() };
+        for _ in 0 .. rng.gen_range(0, 4) {
+            prefab = prefab.rotated();
+        }
+        prefab
+    }
+}
+
+#[derive(Debug)]
+pub enum PrefabError {
+    Io(io::Error),
+    Parse { name: String, line: usize, reason: String },
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PrefabError::Io(ref err) => write!(f, "could not read prefabs: {}", err),
+            PrefabError::Parse { ref name, line, ref reason } => write!(f, "prefab '{}' line {}: {}", name, line, reason),
+        }
+    }
+}
+
+impl Error for PrefabError {}
+
+impl From<io::Error> for PrefabError {
+    fn from(err: io::Error) -> Self {
+        PrefabError::Io(err)
+    }
+}
+
+//All the prefabs the game knows about.
+#[derive(Clone, Debug)]
+pub struct PrefabLibrary {
+    pub prefabs: Vec<Prefab>,
+}
+
+impl PrefabLibrary {
+    pub fn empty() -> Self {
+        PrefabLibrary { prefabs: vec![] }
+    }
+
+    //Every .txt file in the directory is a prefab, named after the file.
+    pub fn load_dir(path: &Path) -> Result<PrefabLibrary, PrefabError> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |extension| extension == "txt") {
+                paths.push(path);
+            }
+        }
+        //read_dir has no order, sort so the same seed picks the same prefabs everywhere
+        paths.sort();
+
+        let mut prefabs = vec![];
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let mut text = String::new();
+            File::open(&path)?.read_to_string(&mut text)?;
+            prefabs.push(Prefab::parse(&name, &text)?);
+        }
+        Ok(PrefabLibrary { prefabs: prefabs })
+    }
+}
+
+//Runs another generator and stamps prefabs on top of what it made.
+pub struct PrefabGenerator {
+    pub inner: Box<dyn MapGenerator>,
+    pub prefabs: PrefabLibrary,
+}
+
+//Put the prefabs on top of a generator. Without any prefabs the generator is left as it is.
+pub fn with_prefabs(generator: Box<dyn MapGenerator>, prefabs: PrefabLibrary) -> Box<dyn MapGenerator> {
+    if prefabs.prefabs.is_empty() {
+        return generator;
+    }
+    Box::new(PrefabGenerator { inner: generator, prefabs: prefabs })
+}
+
+impl MapGenerator for PrefabGenerator {
+    //saves only need to know what builds the levels underneath
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut GameRng) -> GeneratedMap {
+        let mut generated = self.inner.generate(width, height, rng);
+        stamp_prefabs(&mut generated, &self.prefabs.prefabs, rng);
+        generated
+    }
+}
+
+//Stamps a few random prefabs (turned and flipped at random) onto the map.
+//A prefab is only put down where it stays inside the outer wall, doesn't overlap another
+//prefab or the start and doesn't cut anything off from the start.
+pub fn stamp_prefabs(generated: &mut GeneratedMap, prefabs: &[Prefab], rng: &mut GameRng) {
+    let (width, height) = (generated.map.width(), generated.map.height());
+    let mut stamped: Vec<Rect> = vec![];
+
+    for _ in 0 .. PREFABS_PER_LEVEL {
+        let prefab = match rng.choose(prefabs) {
+            Some(prefab) => prefab.random_orientation(rng),
+            None => return,
+        };
+        if prefab.width > width - 2 || prefab.height > height - 2 {
+            continue;
+        }
+
+        for _ in 0 .. ATTEMPTS_PER_PREFAB {
+            let x = rng.gen_range(1, width - prefab.width);
+            let y = rng.gen_range(1, height - prefab.height);
+            //x2 / y2 are the last cell of the prefab
+            let area = Rect::new(x, y, prefab.width - 1, prefab.height - 1);
+            let (start_x, start_y) = generated.start;
+            let covers_start = start_x >= area.x1 && start_x <= area.x2 && start_y >= area.y1 && start_y <= area.y2;
+            if covers_start || stamped.iter().any(|other| area.intersects_with(other)) {
+                continue;
+            }
+            if try_stamp(generated, &prefab, x, y) {
+                stamped.push(area);
+                break;
+            }
+        }
+    }
+
+    //the prefab walls might have gone over some of them
+    let map = &generated.map;
+    generated.spawn_points.retain(|&(x, y)| !map.at(x, y).blocked);
+}
+
+//Stamps the prefab with its top left corner at (x, y) and keeps it if everything that could
+//reach the start before still can, and so can all of the prefab's own floor.
+//Closed doors count as open here since they can be opened.
+//Otherwise the map is put back the way it was.
+fn try_stamp(generated: &mut GeneratedMap, prefab: &Prefab, x: i32, y: i32) -> bool {
+    let start = generated.start;
+    let reachable_before = reachable_from(&generated.map, start);
+
+    let mut replaced = vec![];
+    for py in 0 .. prefab.height {
+        for px in 0 .. prefab.width {
+            if let Some(tile) = prefab.at(px, py).tile() {
+                replaced.push((x + px, y + py, *generated.map.at(x + px, y + py)));
+                generated.map.set(x + px, y + py, tile);
+            }
+        }
+    }
+
+    let map = &generated.map;
+    let reachable = reachable_from(map, start);
+    let index = |x: i32, y: i32| (y * map.width() + x) as usize;
+    let kept_everything = (0 .. map.height())
+        .flat_map(|y| (0 .. map.width()).map(move |x| (x, y)))
+        .all(|(x, y)| !reachable_before[index(x, y)] || !map.at(x, y).is_passable() || reachable[index(x, y)]);
+    let connected = replaced.iter().all(|&(x, y, _)| !map.at(x, y).is_passable() || reachable[index(x, y)]);
+
+    if !(kept_everything && connected) {
+        for (x, y, tile) in replaced {
+            generated.map.set(x, y, tile);
+        }
+        return false;
+    }
+
+    for py in 0 .. prefab.height {
+        for px in 0 .. prefab.width {
+            match prefab.at(px, py) {
+                PrefabCell::Monster => generated.monster_spots.push((x + px, y + py)),
+                PrefabCell::Item => generated.item_spots.push((x + px, y + py)),
+                _ => {}
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::map::*;
+
+    fn same_cells(a: &Prefab, b: &Prefab) -> bool {
+        a.width == b.width && a.height == b.height && a.cells == b.cells
+    }
+
+    //no symmetry at all so every turn and flip looks diffrent
+    fn lopsided() -> Prefab {
+        Prefab::parse("lopsided", "#.\n..\n.M").unwrap()
+    }
+
+    //a straight corridor from (1, 1) to (5, 1), starting at its left end
+    fn corridor() -> GeneratedMap {
+        let mut map = Map::new(7, 3, Tile::wall());
+        for x in 1 .. 6 {
+            map.set(x, 1, Tile::empty());
+        }
+        GeneratedMap {
+            map: map,
+            start: (1, 1),
+            regions: vec![],
+            spawn_points: vec![],
+            monster_spots: vec![],
+            item_spots: vec![],
+        }
+    }
+
+    fn kinds(map: &Map) -> Vec<TileKind> {
+        (0 .. map.height()).flat_map(|y| (0 .. map.width()).map(move |x| (x, y)))
+            .map(|(x, y)| map.at(x, y).kind)
+            .collect()
+    }
+
+    #[test]
+    fn four_turns_go_all_the_way_around() {
+        let prefab = lopsided();
+        let turned = prefab.rotated().rotated().rotated().rotated();
+        assert!(same_cells(&prefab, &turned));
+        assert!(!same_cells(&prefab, &prefab.rotated().rotated()));
+    }
+
+    #[test]
+    fn mirroring_twice_changes_nothing() {
+        let prefab = lopsided();
+        assert!(!same_cells(&prefab, &prefab.mirrored()));
+        assert!(same_cells(&prefab, &prefab.mirrored().mirrored()));
+    }
+
+    #[test]
+    fn rotates_clockwise() {
+        //#.        ..#
+        //..   ->   M..
+        //.M
+        let turned = lopsided().rotated();
+        assert_eq!((turned.width, turned.height), (3, 2));
+        assert_eq!(turned.at(2, 0), PrefabCell::Tile(TileKind::Wall));
+        assert_eq!(turned.at(0, 1), PrefabCell::Monster);
+        assert_eq!(turned.at(0, 0), PrefabCell::Tile(TileKind::Floor));
+    }
+
+    #[test]
+    fn wont_cut_a_corridor_in_two() {
+        let mut generated = corridor();
+        let before = kinds(&generated.map);
+        let wall = Prefab::parse("wall", "#").unwrap();
+        assert!(!try_stamp(&mut generated, &wall, 3, 1));
+        assert_eq!(kinds(&generated.map), before);
+    }
+
+    #[test]
+    fn stamps_where_nothing_gets_cut_off() {
+        let mut generated = corridor();
+        let alcove = Prefab::parse("alcove", "I").unwrap();
+        assert!(try_stamp(&mut generated, &alcove, 3, 2));
+        assert_eq!(generated.map.at(3, 2).kind, TileKind::Floor);
+        assert_eq!(generated.item_spots, vec![(3, 2)]);
+    }
+}
@@ -0,0 +1,304 @@
+#![allow(dead_code)]
+
+use tcod::console::*;
+use tcod::colors;
+use tcod::Color;
+
+use game::object::*;
+use game::tile::*;
+use game::state::*;
+
+//Which font / draw data the game is shown with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    Ascii,
+    Tileset,
+}
+
+impl RenderMode {
+    pub fn toggled(self) -> RenderMode {
+        match self {
+            RenderMode::Ascii => RenderMode::Tileset,
+            RenderMode::Tileset => RenderMode::Ascii,
+        }
+    }
+
+    pub fn renderer(self, width: i32, height: i32) -> Box<dyn Renderer> {
+        match self {
+            RenderMode::Ascii => Box::new(AsciiRenderer::new(width, height)),
+            RenderMode::Tileset => Box::new(TilesetRenderer::new(width, height)),
+        }
+    }
+}
+
+//Draws the map area. Picking what is visible is done once in draw_map(),
+//a renderer only decides how a single object or tile looks.
+//Each mode is its own type so there is no branching per object, and each one
+//only looks at its own draw info on the object / tile kind.
+pub trait Renderer {
+    fn clear(&mut self);
+    fn draw_object(&mut self, object: &Object);
+    //visible is false for tiles that are only remembered
+    fn draw_tile(&mut self, x: i32, y: i32, kind: TileKind, visible: bool);
+    //copy what was drawn onto the window. Renderers without a console have nothing to copy
+    fn blit_to(&self, _root: &mut Root, _x: i32, _y: i32) {}
+}
+
+pub fn draw_map<R: Renderer + ?Sized>(renderer: &mut R, state: &GameState) {
+    let map = &state.map;
+    renderer.clear();
+
+    //tiles first, the objects go on top of them
+    for x in 0 .. map.width() {
+        for y in 0 .. map.height() {
+            let cell = map.at(x,y);
+            if !cell.explored {
+                // show explored tiles only (any visible tile is explored already)
+                continue;
+            }
+            renderer.draw_tile(x, y, cell.kind, state.is_in_fov(x, y));
+        }
+    }
+
+    //draw objects that don't block (corpses) first so anything standing on top of them is visible
+    let mut to_draw: Vec<_> = state.objects.iter().collect();
+    to_draw.sort_by_key(|object| object.blocks);
+
+    for object in to_draw {
+        let visible = state.is_in_fov(object.x,object.y)
+            || (object.always_visible && map.at(object.x,object.y).explored);
+        if visible {
+            renderer.draw_object(object);
+        }
+    }
+}
+
+//Draws objects with their AsciiDrawInfo, for the arial10x10 font.
+pub struct AsciiRenderer {
+    con: Offscreen,
+}
+
+impl AsciiRenderer {
+    pub fn new(width: i32, height: i32) -> Self {
+        AsciiRenderer { con: Offscreen::new(width, height) }
+    }
+}
+
+impl Renderer for AsciiRenderer {
+    fn clear(&mut self) {
+        self.con.set_default_background(colors::BLACK);
+        self.con.clear();
+    }
+
+    fn draw_object(&mut self, object: &Object) {
+        self.con.set_default_foreground(object.ascii.color);
+        self.con.put_char(object.x, object.y, object.ascii.char, BackgroundFlag::None);
+    }
+
+    fn draw_tile(&mut self, x: i32, y: i32, kind: TileKind, visible: bool) {
+        let look = kind.ascii();
+        let (foreground, background) = look.colors(visible);
+        self.con.put_char_ex(x, y, look.glyph, foreground, background);
+    }
+
+    fn blit_to(&self, root: &mut Root, x: i32, y: i32) {
+        blit(&self.con, (0, 0), (self.con.width(), self.con.height()), root, (x, y), 1.0, 1.0);
+    }
+}
+
+//Draws objects with their TilesetDrawInfo, for the TiledFont font.
+pub struct TilesetRenderer {
+    con: Offscreen,
+}
+
+impl TilesetRenderer {
+    pub fn new(width: i32, height: i32) -> Self {
+        TilesetRenderer { con: Offscreen::new(width, height) }
+    }
+}
+
+impl Renderer for TilesetRenderer {
+    fn clear(&mut self) {
+        self.con.set_default_background(colors::BLACK);
+        self.con.clear();
+    }
+
+    //tiles come with their own background, same as the map tiles
+    fn draw_object(&mut self, object: &Object) {
+        let look = object.tile;
+        self.con.put_char_ex(object.x, object.y, look.char, look.foreground, look.background);
+    }
+
+    fn draw_tile(&mut self, x: i32, y: i32, kind: TileKind, visible: bool) {
+        let look = kind.tileset();
+        let (foreground, background) = look.colors(visible);
+        self.con.put_char_ex(x, y, look.glyph, foreground, background);
+    }
+
+    fn blit_to(&self, root: &mut Root, x: i32, y: i32) {
+        blit(&self.con, (0, 0), (self.con.width(), self.con.height()), root, (x, y), 1.0, 1.0);
+    }
+}
+
+//One cell of the TestRenderer's grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+const EMPTY_CELL: Cell = Cell { glyph: ' ', foreground: colors::WHITE, background: colors::BLACK };
+
+//Keeps what would have been drawn in memory (using the draw info of its mode) so tests
+//can check it without opening a window.
+pub struct TestRenderer {
+    mode: RenderMode,
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+}
+
+impl TestRenderer {
+    pub fn new(width: i32, height: i32) -> Self {
+        TestRenderer::with_mode(RenderMode::Ascii, width, height)
+    }
+
+    pub fn with_mode(mode: RenderMode, width: i32, height: i32) -> Self {
+        TestRenderer {
+            mode: mode,
+            width: width,
+            height: height,
+            cells: vec![EMPTY_CELL; (width * height) as usize],
+        }
+    }
+
+    pub fn at(&self, x: i32, y: i32) -> Cell {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    //the glyphs of one row, handy for comparing against a string
+    pub fn row(&self, y: i32) -> String {
+        (0 .. self.width).map(|x| self.at(x, y).glyph).collect()
+    }
+
+    fn at_mut(&mut self, x: i32, y: i32) -> Option<&mut Cell> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(&mut self.cells[(y * self.width + x) as usize])
+    }
+}
+
+impl Renderer for TestRenderer {
+    fn clear(&mut self) {
+        for cell in &mut self.cells {
+            *cell = EMPTY_CELL;
+        }
+    }
+
+    fn draw_object(&mut self, object: &Object) {
+        let mode = self.mode;
+        if let Some(cell) = self.at_mut(object.x, object.y) {
+            match mode {
+                //ascii keeps the background of the tile underneath
+                RenderMode::Ascii => {
+                    cell.glyph = object.ascii.char;
+                    cell.foreground = object.ascii.color;
+                }
+                RenderMode::Tileset => {
+                    *cell = Cell { glyph: object.tile.char, foreground: object.tile.foreground, background: object.tile.background };
+                }
+            }
+        }
+    }
+
+    fn draw_tile(&mut self, x: i32, y: i32, kind: TileKind, visible: bool) {
+        let look = match self.mode {
+            RenderMode::Ascii => kind.ascii(),
+            RenderMode::Tileset => kind.tileset(),
+        };
+        let (foreground, background) = look.colors(visible);
+        if let Some(cell) = self.at_mut(x, y) {
+            *cell = Cell { glyph: look.glyph, foreground: foreground, background: background };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::PLAYER;
+    use game::draw_info::*;
+
+    fn render(state: &GameState) -> TestRenderer {
+        let mut renderer = TestRenderer::new(state.map.width(), state.map.height());
+        draw_map(&mut renderer, state);
+        renderer
+    }
+
+    #[test]
+    fn draws_the_player_on_a_lit_floor() {
+        let state = GameState::test_game();
+        let (x, y) = state.objects[PLAYER].pos();
+        let cell = render(&state).at(x, y);
+        assert_eq!(cell.glyph, '@');
+        assert_eq!(cell.foreground, colors::WHITE);
+        assert_eq!(cell.background, TileKind::Floor.ascii().light);
+    }
+
+    #[test]
+    fn leaves_unexplored_tiles_blank() {
+        let mut state = GameState::test_game();
+        let (x, y) = state.objects[PLAYER].pos();
+        //hide a monster in a tile that has never been seen
+        let hidden = (0 .. state.map.width())
+            .flat_map(|x| (0 .. state.map.height()).map(move |y| (x, y)))
+            .find(|&(x, y)| !state.map.at(x, y).explored)
+            .unwrap();
+        let potion = Object::new(hidden.0, hidden.1, ascii::healing_potion, *tileset::healing_potion, "healing potion", false);
+        state.objects.push(potion);
+
+        let renderer = render(&state);
+        assert_eq!(renderer.at(hidden.0, hidden.1), EMPTY_CELL);
+        assert_eq!(renderer.at(x, y).glyph, '@');
+    }
+
+    fn render_as(mode: RenderMode, state: &GameState) -> TestRenderer {
+        let mut renderer = TestRenderer::with_mode(mode, state.map.width(), state.map.height());
+        draw_map(&mut renderer, state);
+        renderer
+    }
+
+    #[test]
+    fn toggling_the_mode_only_changes_how_the_game_looks() {
+        let mut state = GameState::test_game();
+        state.step(Action::Wait);
+        let (x, y) = state.objects[PLAYER].pos();
+        let ascii = render_as(RenderMode::Ascii, &state);
+        assert_eq!(ascii.at(x, y).glyph, '@');
+
+        //play() makes a new renderer for the toggled mode and keeps drawing the same state with it
+        let mode = RenderMode::Ascii.toggled();
+        assert_eq!(mode, RenderMode::Tileset);
+        let tiles = render_as(mode, &state);
+        assert_eq!(tiles.at(x, y).glyph, state.objects[PLAYER].tile.char);
+        assert_eq!(tiles.at(x, y).background, state.objects[PLAYER].tile.background);
+
+        //nothing about the game changed, toggling back draws the exact same thing
+        assert_eq!(state.tick, 1);
+        assert_eq!(state.objects[PLAYER].pos(), (x, y));
+        assert_eq!(render_as(mode.toggled(), &state).cells, ascii.cells);
+    }
+
+    #[test]
+    fn tileset_objects_bring_their_own_background() {
+        let mut state = GameState::test_game();
+        let (x, y) = state.objects[PLAYER].pos();
+        state.objects[PLAYER].tile.background = colors::DARK_RED;
+
+        assert_eq!(render_as(RenderMode::Tileset, &state).at(x, y).background, colors::DARK_RED);
+        //ascii objects still show the floor they stand on
+        assert_eq!(render_as(RenderMode::Ascii, &state).at(x, y).background, TileKind::Floor.ascii().light);
+    }
+}
@@ -0,0 +1,31 @@
+extern crate rand;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+//Every random decision the game makes (map generation, spawning, etc.) should go through
+//a GameRng instead of rand::thread_rng() so the same seed always rebuilds the same dungeon.
+//XorShift is used instead of StdRng since StdRng picks a diffrent algorithm depending on
+//the platform (Isaac vs Isaac64) and we want a seed from a bug report to work everywhere.
+pub type GameRng = XorShiftRng;
+
+pub fn from_seed(seed: u32) -> GameRng {
+    //XorShift needs 4 words of state and panics if they are all zero.
+    //Spread the single seed value out over the 4 words so nearby seeds still give
+    //very diffrent dungeons.
+    let mut state = seed;
+    let mut words = [0u32; 4];
+    for word in words.iter_mut() {
+        state = state.wrapping_mul(0x9E37_79B9).wrapping_add(0x7F4A_7C15);
+        *word = state ^ (state >> 16);
+    }
+    if words.iter().all(|&word| word == 0) {
+        words[0] = 1;
+    }
+    XorShiftRng::from_seed(words)
+}
+
+//Used when no seed was given. Pulls from the thread rng so every run is diffrent
+//but the value can still be printed and fed back in with --seed.
+pub fn random_seed() -> u32 {
+    rand::thread_rng().gen()
+}
@@ -0,0 +1,770 @@
+use std::char;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::{FromStr, Lines, SplitWhitespace};
+
+use tcod::Color;
+
+use game::object::*;
+use game::map::*;
+use game::tile::*;
+use game::draw_info::*;
+use game::state::*;
+use game::ai::*;
+use game::messages::*;
+use game::monsters::*;
+use game::mapgen::*;
+use game::prefab::*;
+
+//Save files are plain text so they are easy to poke at when something goes wrong.
+//Bump this whenever the layout below changes so old saves get rejected instead of misread.
+pub const SAVE_VERSION: u32 = 10;
+const SAVE_HEADER: &'static str = "roguelike-save";
+
+//Layout (one record per line, fields seperated by spaces):
+//  roguelike-save <version>
+//  tick <tick>
+//  depth <depth>
+//  generator <map generator name>
+//  <the current level>
+//  inventory <count>
+//  <count objects, see below>
+//  messages <count>
+//  message <turn> <color> <text>   (count times, oldest first)
+//  levels <count>
+//  level <depth>            (once for every stored level)
+//  <the stored level>
+//
+//Where a level is:
+//  map <width> <height>
+//  <height lines of width tiles, two chars per tile: the kind (see TileKind::to_char()) and 1 if it is explored or 0 if not>
+//  objects <count>
+//  object <x> <y> <blocks> <alive> <always visible> <ascii char> <ascii color> <tile char> <tile fg> <tile bg> <name>
+//  stairs <down|up>                                        (optional, belongs to the object above it)
+//  fighter <max hp> <hp> <defense> <power> <xp> <on death>     (optional, belongs to the object above it)
+//  ai <name>                                               (optional, belongs to the object above it)
+//  item <kind>                                             (optional, belongs to the object above it)
+//  equipment <slot> <equipped> <power bonus> <defense bonus>  (optional, belongs to the object above it)
+//The current level's objects start with the player.
+//Whether a tile blocks isn't saved, it always comes from its kind.
+//chars are saved as their u32 value since the tileset uses chars above 255.
+//colors are saved as "r g b".
+//Text (names and messages) is saved as one field, see text_to_field().
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    //the file was written by a diffrent version of the game
+    UnsupportedVersion(u32),
+    //the file doesn't look like a save file or was cut short
+    Corrupt { line: usize, reason: String },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref err) => write!(f, "could not access save file: {}", err),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "save file is version {} but this game only reads version {}", version, SAVE_VERSION)
+            }
+            SaveError::Corrupt { line, ref reason } => write!(f, "save file is corrupt (line {}): {}", line, reason),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+pub fn save_game(path: &Path, state: &GameState) -> Result<(), SaveError> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(out, "{} {}", SAVE_HEADER, SAVE_VERSION)?;
+    writeln!(out, "tick {}", state.tick)?;
+    writeln!(out, "depth {}", state.depth)?;
+    writeln!(out, "generator {}", state.generator.name())?;
+
+    write_map(&mut out, &state.map)?;
+    write_objects(&mut out, "objects", &state.objects)?;
+    write_objects(&mut out, "inventory", &state.inventory)?;
+
+    writeln!(out, "messages {}", state.messages.len())?;
+    for message in state.messages.iter() {
+        writeln!(out, "message {} {} {}", message.turn, color_to_string(message.color), text_to_field(&message.text))?;
+    }
+
+    //sorted so the same game always gives the same file
+    let mut depths: Vec<_> = state.levels.keys().cloned().collect();
+    depths.sort();
+    writeln!(out, "levels {}", depths.len())?;
+    for depth in depths {
+        let level = &state.levels[&depth];
+        writeln!(out, "level {}", depth)?;
+        write_map(&mut out, &level.map)?;
+        write_objects(&mut out, "objects", &level.objects)?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+fn write_map<W: Write>(out: &mut W, map: &Map) -> io::Result<()> {
+    writeln!(out, "map {} {}", map.width(), map.height())?;
+    for y in 0 .. map.height() {
+        let mut row = String::with_capacity(map.width() as usize * 2);
+        for x in 0 .. map.width() {
+            let tile = map.at(x, y);
+            row.push(tile.kind.to_char());
+            row.push(if tile.explored { '1' } else { '0' });
+        }
+        writeln!(out, "{}", row)?;
+    }
+    Ok(())
+}
+
+fn write_objects<W: Write>(out: &mut W, label: &str, objects: &[Object]) -> io::Result<()> {
+    writeln!(out, "{} {}", label, objects.len())?;
+    for object in objects {
+        writeln!(out, "object {} {} {} {} {} {} {} {} {} {} {}",
+            object.x, object.y,
+            object.blocks as u8, object.alive as u8, object.always_visible as u8,
+            object.ascii.char as u32, color_to_string(object.ascii.color),
+            object.tile.char as u32, color_to_string(object.tile.foreground), color_to_string(object.tile.background),
+            text_to_field(&object.name))?;
+        if let Some(stairs) = object.stairs {
+            writeln!(out, "stairs {}", stairs_to_str(stairs))?;
+        }
+        if let Some(fighter) = object.fighter {
+            writeln!(out, "fighter {} {} {} {} {} {}",
+                fighter.max_hp, fighter.hp, fighter.defense, fighter.power, fighter.xp,
+                death_callback_to_str(fighter.on_death))?;
+        }
+        if let Some(ref ai) = object.ai {
+            writeln!(out, "ai {}", ai.name())?;
+        }
+        if let Some(item) = object.item {
+            writeln!(out, "item {}", item_to_str(item))?;
+        }
+        if let Some(equipment) = object.equipment {
+            writeln!(out, "equipment {} {} {} {}",
+                slot_to_str(equipment.slot), equipment.equipped as u8,
+                equipment.power_bonus, equipment.defense_bonus)?;
+        }
+    }
+    Ok(())
+}
+
+//The monster table and prefabs aren't part of the save, they come from the data files like for a new game.
+pub fn load_game(path: &Path, monsters: MonsterTable, prefabs: PrefabLibrary) -> Result<GameState, SaveError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+
+    let mut reader = SaveReader::new(&contents);
+
+    let mut header = reader.record(SAVE_HEADER)?;
+    let version: u32 = reader.field(&mut header, "version")?;
+    if version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    let mut fields = reader.record("tick")?;
+    let tick = reader.field(&mut fields, "tick")?;
+
+    let mut fields = reader.record("depth")?;
+    let depth = reader.field(&mut fields, "depth")?;
+
+    let mut fields = reader.record("generator")?;
+    let generator = match fields.next() {
+        Some(name) => match generator_from_name(name) {
+            Some(generator) => with_prefabs(generator, prefabs),
+            None => return Err(reader.corrupt(format!("'{}' is not a map generator", name))),
+        },
+        None => return Err(reader.corrupt("missing map generator".into())),
+    };
+
+    let map = read_map(&mut reader)?;
+    let objects = read_objects(&mut reader, "objects")?;
+    check_positions(&reader, &map, &objects)?;
+    //the game looks the player up by position in the list, see PLAYER
+    let player_first = objects.first()
+        .and_then(|object| object.fighter)
+        .map_or(false, |fighter| fighter.on_death == DeathCallback::Player);
+    if !player_first {
+        return Err(reader.corrupt("the first object must be the player".into()));
+    }
+    let inventory = read_objects(&mut reader, "inventory")?;
+
+    let mut fields = reader.record("messages")?;
+    let message_count: usize = reader.field(&mut fields, "message count")?;
+    let mut messages = Messages::new();
+    for _ in 0 .. message_count {
+        let mut fields = reader.record("message")?;
+        let turn = reader.field(&mut fields, "message turn")?;
+        let color = reader.color(&mut fields, "message color")?;
+        let text = reader.text(&mut fields, "message text")?;
+        messages.push(Message { text: text, color: color, turn: turn });
+    }
+
+    let mut fields = reader.record("levels")?;
+    let level_count: usize = reader.field(&mut fields, "level count")?;
+    let mut levels = HashMap::new();
+    for _ in 0 .. level_count {
+        let mut fields = reader.record("level")?;
+        let level_depth: i32 = reader.field(&mut fields, "level depth")?;
+        if level_depth == depth || levels.contains_key(&level_depth) {
+            return Err(reader.corrupt(format!("depth {} is saved more than once", level_depth)));
+        }
+        let level = Level {
+            map: read_map(&mut reader)?,
+            objects: read_objects(&mut reader, "objects")?,
+        };
+        check_positions(&reader, &level.map, &level.objects)?;
+        //the player comes out of the stairs leading back the way they came, see GameState::take_stairs()
+        let back = if level_depth < depth { Stairs::Down } else { Stairs::Up };
+        if !level.objects.iter().any(|object| object.stairs == Some(back)) {
+            return Err(reader.corrupt(format!("depth {} has no stairs leading back", level_depth)));
+        }
+        levels.insert(level_depth, level);
+    }
+
+    Ok(GameState::from_parts(map, objects, inventory, messages, tick, depth, levels, monsters, generator))
+}
+
+fn read_map(reader: &mut SaveReader) -> Result<Map, SaveError> {
+    let mut fields = reader.record("map")?;
+    let width: i32 = reader.field(&mut fields, "map width")?;
+    let height: i32 = reader.field(&mut fields, "map height")?;
+    if width <= 0 || height <= 0 {
+        return Err(reader.corrupt(format!("invalid map size {}x{}", width, height)));
+    }
+
+    let mut map = Map::new(width, height, Tile::wall());
+    for y in 0 .. height {
+        let row: Vec<char> = reader.line()?.chars().collect();
+        if row.len() != width as usize * 2 {
+            return Err(reader.corrupt(format!("expected {} tiles in map row but found {}", width, row.len() / 2)));
+        }
+        for (x, pair) in row.chunks(2).enumerate() {
+            let mut tile = match TileKind::from_char(pair[0]) {
+                Some(kind) => Tile::new(kind),
+                None => return Err(reader.corrupt(format!("'{}' is not a valid tile", pair[0]))),
+            };
+            tile.explored = match pair[1] {
+                '0' => false,
+                '1' => true,
+                _ => return Err(reader.corrupt(format!("'{}' is not a valid explored flag", pair[1]))),
+            };
+            map.set(x as i32, y, tile);
+        }
+    }
+    Ok(map)
+}
+
+fn read_objects(reader: &mut SaveReader, label: &str) -> Result<Vec<Object>, SaveError> {
+    let mut fields = reader.record(label)?;
+    let count: usize = reader.field(&mut fields, "object count")?;
+
+    let mut objects = Vec::with_capacity(count);
+    for _ in 0 .. count {
+        objects.push(read_object(reader)?);
+    }
+    Ok(objects)
+}
+
+//everything on a level has to be standing on its map
+fn check_positions(reader: &SaveReader, map: &Map, objects: &[Object]) -> Result<(), SaveError> {
+    match objects.iter().find(|object| !map.in_bounds(object.x, object.y)) {
+        Some(object) => Err(reader.corrupt(format!("{} at {},{} is outside of the map", object.name, object.x, object.y))),
+        None => Ok(()),
+    }
+}
+
+fn read_object(reader: &mut SaveReader) -> Result<Object, SaveError> {
+    let mut fields = reader.record("object")?;
+    let x = reader.field(&mut fields, "x")?;
+    let y = reader.field(&mut fields, "y")?;
+    let blocks = reader.flag(&mut fields, "blocks")?;
+    let alive = reader.flag(&mut fields, "alive")?;
+    let always_visible = reader.flag(&mut fields, "always visible")?;
+
+    let ascii = AsciiDrawInfo {
+        char: reader.char_field(&mut fields, "ascii char")?,
+        color: reader.color(&mut fields, "ascii color")?,
+    };
+    let tile = TilesetDrawInfo {
+        char: reader.char_field(&mut fields, "tile char")?,
+        foreground: reader.color(&mut fields, "tile foreground")?,
+        background: reader.color(&mut fields, "tile background")?,
+    };
+
+    let name = reader.text(&mut fields, "name")?;
+    if name.is_empty() {
+        return Err(reader.corrupt("object is missing a name".into()));
+    }
+
+    let mut object = Object::new(x, y, ascii, tile, &name, blocks);
+    object.alive = alive;
+    object.always_visible = always_visible;
+
+    if reader.next_record_is("stairs") {
+        let mut fields = reader.record("stairs")?;
+        object.stairs = match fields.next() {
+            Some(name) => match str_to_stairs(name) {
+                Some(stairs) => Some(stairs),
+                None => return Err(reader.corrupt(format!("'{}' is not a valid stairs direction", name))),
+            },
+            None => return Err(reader.corrupt("missing stairs direction".into())),
+        };
+    }
+
+    if reader.next_record_is("fighter") {
+        let mut fields = reader.record("fighter")?;
+        let max_hp = reader.field(&mut fields, "max hp")?;
+        let hp = reader.field(&mut fields, "hp")?;
+        let defense = reader.field(&mut fields, "defense")?;
+        let power = reader.field(&mut fields, "power")?;
+        let xp = reader.field(&mut fields, "xp")?;
+        let on_death = match fields.next() {
+            Some(name) => match str_to_death_callback(name) {
+                Some(on_death) => on_death,
+                None => return Err(reader.corrupt(format!("'{}' is not a valid death callback", name))),
+            },
+            None => return Err(reader.corrupt("missing death callback".into())),
+        };
+        object.fighter = Some(Fighter {
+            max_hp: max_hp,
+            hp: hp,
+            defense: defense,
+            power: power,
+            xp: xp,
+            on_death: on_death,
+        });
+    }
+
+    if reader.next_record_is("ai") {
+        let mut fields = reader.record("ai")?;
+        object.ai = match fields.next() {
+            Some(name) => match ai_from_name(name) {
+                Some(ai) => Some(ai),
+                None => return Err(reader.corrupt(format!("'{}' is not a known ai", name))),
+            },
+            None => return Err(reader.corrupt("missing ai name".into())),
+        };
+    }
+
+    if reader.next_record_is("item") {
+        let mut fields = reader.record("item")?;
+        object.item = match fields.next() {
+            Some(name) => match str_to_item(name) {
+                Some(item) => Some(item),
+                None => return Err(reader.corrupt(format!("'{}' is not a valid item", name))),
+            },
+            None => return Err(reader.corrupt("missing item kind".into())),
+        };
+    }
+
+    if reader.next_record_is("equipment") {
+        let mut fields = reader.record("equipment")?;
+        let slot = match fields.next() {
+            Some(name) => match str_to_slot(name) {
+                Some(slot) => slot,
+                None => return Err(reader.corrupt(format!("'{}' is not a valid equipment slot", name))),
+            },
+            None => return Err(reader.corrupt("missing equipment slot".into())),
+        };
+        object.equipment = Some(Equipment {
+            slot: slot,
+            equipped: reader.flag(&mut fields, "equipped")?,
+            power_bonus: reader.field(&mut fields, "power bonus")?,
+            defense_bonus: reader.field(&mut fields, "defense bonus")?,
+        });
+    }
+
+    Ok(object)
+}
+
+fn item_to_str(item: Item) -> &'static str {
+    match item {
+        Item::Heal => "heal",
+        Item::Lightning => "lightning",
+        Item::Equipment => "equipment",
+    }
+}
+
+fn str_to_item(name: &str) -> Option<Item> {
+    match name {
+        "heal" => Some(Item::Heal),
+        "lightning" => Some(Item::Lightning),
+        "equipment" => Some(Item::Equipment),
+        _ => None,
+    }
+}
+
+//slots have spaces in their display names so they get their own save names
+fn slot_to_str(slot: Slot) -> &'static str {
+    match slot {
+        Slot::RightHand => "right_hand",
+        Slot::LeftHand => "left_hand",
+    }
+}
+
+fn str_to_slot(name: &str) -> Option<Slot> {
+    match name {
+        "right_hand" => Some(Slot::RightHand),
+        "left_hand" => Some(Slot::LeftHand),
+        _ => None,
+    }
+}
+
+fn stairs_to_str(stairs: Stairs) -> &'static str {
+    match stairs {
+        Stairs::Down => "down",
+        Stairs::Up => "up",
+    }
+}
+
+fn str_to_stairs(name: &str) -> Option<Stairs> {
+    match name {
+        "down" => Some(Stairs::Down),
+        "up" => Some(Stairs::Up),
+        _ => None,
+    }
+}
+
+fn death_callback_to_str(on_death: DeathCallback) -> &'static str {
+    match on_death {
+        DeathCallback::Player => "player",
+        DeathCallback::Monster => "monster",
+    }
+}
+
+fn str_to_death_callback(name: &str) -> Option<DeathCallback> {
+    match name {
+        "player" => Some(DeathCallback::Player),
+        "monster" => Some(DeathCallback::Monster),
+        _ => None,
+    }
+}
+
+//Text goes on the line as a single field so it can't get mixed up with the fields around it.
+//Spaces become '_' to keep it readable, anything else that would split the field
+//(and '_' and '\' themselves) gets escaped as \u{<hex code>}. Nothing at all is saved as a lone '\'.
+fn text_to_field(text: &str) -> String {
+    if text.is_empty() {
+        return "\\".into();
+    }
+    let mut field = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ' ' => field.push('_'),
+            c if c == '_' || c == '\\' || c.is_whitespace() => field.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => field.push(c),
+        }
+    }
+    field
+}
+
+fn field_to_text(field: &str) -> Option<String> {
+    if field == "\\" {
+        return Some(String::new());
+    }
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '_' => text.push(' '),
+            '\\' => {
+                if chars.next() != Some('u') || chars.next() != Some('{') {
+                    return None;
+                }
+                let mut code = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        digit => code.push(digit),
+                    }
+                }
+                text.push(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)?);
+            }
+            c => text.push(c),
+        }
+    }
+    Some(text)
+}
+
+fn color_to_string(color: Color) -> String {
+    format!("{} {} {}", color.r, color.g, color.b)
+}
+
+//Walks the save file line by line while keeping track of where we are
+//so errors can point at the line that is wrong.
+struct SaveReader<'a> {
+    lines: Peekable<Lines<'a>>,
+    line_number: usize,
+}
+
+impl<'a> SaveReader<'a> {
+    fn new(contents: &'a str) -> Self {
+        SaveReader { lines: contents.lines().peekable(), line_number: 0 }
+    }
+
+    fn corrupt(&self, reason: String) -> SaveError {
+        SaveError::Corrupt { line: self.line_number, reason: reason }
+    }
+
+    fn line(&mut self) -> Result<&'a str, SaveError> {
+        self.line_number += 1;
+        match self.lines.next() {
+            Some(line) => Ok(line),
+            None => Err(self.corrupt("unexpected end of file".into())),
+        }
+    }
+
+    //used for optional records. Looks at the next line without consuming it.
+    fn next_record_is(&mut self, name: &str) -> bool {
+        match self.lines.peek() {
+            Some(line) => line.split_whitespace().next() == Some(name),
+            None => false,
+        }
+    }
+
+    //reads the next line and checks that it starts with the expected record name.
+    //returns the rest of the fields on that line.
+    fn record(&mut self, name: &str) -> Result<SplitWhitespace<'a>, SaveError> {
+        let line = self.line()?;
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some(found) if found == name => Ok(fields),
+            Some(found) => Err(self.corrupt(format!("expected '{}' but found '{}'", name, found))),
+            None => Err(self.corrupt(format!("expected '{}' but found an empty line", name))),
+        }
+    }
+
+    fn field<T: FromStr>(&self, fields: &mut SplitWhitespace, what: &str) -> Result<T, SaveError> {
+        match fields.next() {
+            Some(field) => field.parse().map_err(|_| self.corrupt(format!("'{}' is not a valid {}", field, what))),
+            None => Err(self.corrupt(format!("missing {}", what))),
+        }
+    }
+
+    fn flag(&self, fields: &mut SplitWhitespace, what: &str) -> Result<bool, SaveError> {
+        let value: u8 = self.field(fields, what)?;
+        match value {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(self.corrupt(format!("{} must be 0 or 1", what))),
+        }
+    }
+
+    fn char_field(&self, fields: &mut SplitWhitespace, what: &str) -> Result<char, SaveError> {
+        let value: u32 = self.field(fields, what)?;
+        char::from_u32(value).ok_or_else(|| self.corrupt(format!("{} is not a valid {}", value, what)))
+    }
+
+    fn text(&self, fields: &mut SplitWhitespace, what: &str) -> Result<String, SaveError> {
+        match fields.next() {
+            Some(field) => field_to_text(field).ok_or_else(|| self.corrupt(format!("'{}' is not a valid {}", field, what))),
+            None => Err(self.corrupt(format!("missing {}", what))),
+        }
+    }
+
+    fn color(&self, fields: &mut SplitWhitespace, what: &str) -> Result<Color, SaveError> {
+        let r = self.field(fields, what)?;
+        let g = self.field(fields, what)?;
+        let b = self.field(fields, what)?;
+        Ok(Color { r: r, g: g, b: b })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use tcod::colors;
+    use game::PLAYER;
+
+    fn load(path: &Path) -> Result<GameState, SaveError> {
+        load_game(path, MonsterTable::shipped(), PrefabLibrary::empty())
+    }
+
+    //every test gets its own file since tests run at the same time
+    fn save_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("roguelike-test-{}.sav", name))
+    }
+
+    //saves a new game, lets `change` edit the lines of the file and loads it again
+    fn load_changed<F: Fn(&mut Vec<String>)>(name: &str, change: F) -> Result<GameState, SaveError> {
+        let path = save_path(name);
+        save_game(&path, &GameState::test_game()).unwrap();
+        let mut lines: Vec<String> = fs::read_to_string(&path).unwrap().lines().map(String::from).collect();
+        change(&mut lines);
+        fs::write(&path, lines.join("\n")).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    fn line_starting_with(lines: &[String], start: &str) -> usize {
+        lines.iter().position(|line| line.starts_with(start)).unwrap()
+    }
+
+    fn assert_corrupt(result: Result<GameState, SaveError>) {
+        match result {
+            Err(SaveError::Corrupt { .. }) => {}
+            Err(err) => panic!("expected a corrupt save, got {}", err),
+            Ok(_) => panic!("expected a corrupt save but it loaded"),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut state = GameState::test_game();
+        state.tick = 42;
+        state.depth = 3;
+        state.map.at_mut(1, 1).explored = true;
+
+        let path = save_path("round-trip");
+        save_game(&path, &state).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.tick, 42);
+        assert_eq!(loaded.depth, 3);
+        assert_eq!((loaded.map.width(), loaded.map.height()), (state.map.width(), state.map.height()));
+        for y in 0 .. state.map.height() {
+            for x in 0 .. state.map.width() {
+                assert_eq!(loaded.map.at(x, y).kind, state.map.at(x, y).kind);
+                assert_eq!(loaded.map.at(x, y).explored, state.map.at(x, y).explored);
+            }
+        }
+        assert_eq!(loaded.objects.len(), state.objects.len());
+        for (loaded, object) in loaded.objects.iter().zip(state.objects.iter()) {
+            assert_eq!(loaded.name, object.name);
+            assert_eq!(loaded.pos(), object.pos());
+            assert_eq!(loaded.stairs, object.stairs);
+        }
+        assert_eq!(loaded.objects[PLAYER].name, "player");
+    }
+
+    #[test]
+    fn text_keeps_its_spaces() {
+        let mut state = GameState::test_game();
+        let odd_names = [" two  spaces ", "under_score", "back\\slash \\u{20}", "tab\tand\nnewline"];
+        state.messages = Messages::new();
+        for (i, &name) in odd_names.iter().enumerate() {
+            state.objects[PLAYER + i].name = name.into();
+            state.messages.add(name, colors::WHITE);
+        }
+        state.messages.add("", colors::WHITE);
+
+        let path = save_path("text");
+        save_game(&path, &state).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        for (i, &name) in odd_names.iter().enumerate() {
+            assert_eq!(loaded.objects[PLAYER + i].name, name);
+        }
+        let texts: Vec<&str> = loaded.messages.iter().map(|message| &message.text[..]).collect();
+        assert_eq!(&texts[.. odd_names.len()], &odd_names[..]);
+        assert_eq!(texts[odd_names.len()], "");
+    }
+
+    #[test]
+    fn rejects_broken_text_escapes() {
+        for &field in ["\\x", "\\u{zz}", "\\u{41", "\\u{d800}"].iter() {
+            assert_eq!(field_to_text(field), None, "{}", field);
+        }
+        assert_eq!(field_to_text("\\u{41}_b"), Some("A b".into()));
+    }
+
+    #[test]
+    fn tiles_block_by_their_kind() {
+        let mut state = GameState::test_game();
+        //a floor that claims to be solid and a wall that claims to be see through
+        state.map.set(1, 1, Tile { blocked: true, block_sight: true, ..Tile::empty() });
+        state.map.set(2, 1, Tile { blocked: false, block_sight: false, ..Tile::wall() });
+
+        let path = save_path("tile-flags");
+        save_game(&path, &state).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let (floor, wall) = (loaded.map.at(1, 1), loaded.map.at(2, 1));
+        assert!(!floor.blocked && !floor.block_sight);
+        assert!(wall.blocked && wall.block_sight);
+    }
+
+    #[test]
+    fn rejects_saves_that_dont_start_with_the_player() {
+        let mut state = GameState::test_game();
+        assert!(state.objects.len() > 1);
+        state.objects.swap(PLAYER, PLAYER + 1);
+
+        let path = save_path("player-first");
+        save_game(&path, &state).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_corrupt(loaded);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let result = load_changed("version", |lines| {
+            lines[0] = format!("{} {}", SAVE_HEADER, SAVE_VERSION + 1);
+        });
+        match result {
+            Err(SaveError::UnsupportedVersion(version)) => assert_eq!(version, SAVE_VERSION + 1),
+            _ => panic!("expected an unsupported version"),
+        }
+    }
+
+    #[test]
+    fn rejects_short_map_rows() {
+        assert_corrupt(load_changed("short-row", |lines| {
+            let row = line_starting_with(lines, "map ") + 1;
+            lines[row].pop();
+        }));
+    }
+
+    #[test]
+    fn rejects_unknown_tiles() {
+        assert_corrupt(load_changed("bad-tile", |lines| {
+            let row = line_starting_with(lines, "map ") + 1;
+            lines[row].replace_range(0 .. 1, "X");
+        }));
+    }
+
+    #[test]
+    fn rejects_stored_levels_without_stairs_back() {
+        let mut state = GameState::test_game();
+        let map = Map::new(state.map.width(), state.map.height(), Tile::empty());
+        state.levels.insert(state.depth + 1, Level { map: map, objects: vec![] });
+
+        let path = save_path("no-stairs");
+        save_game(&path, &state).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_corrupt(loaded);
+    }
+
+    #[test]
+    fn rejects_objects_outside_the_map() {
+        assert_corrupt(load_changed("outside", |lines| {
+            let object = line_starting_with(lines, "object ");
+            let rest = lines[object].splitn(4, ' ').nth(3).unwrap().to_string();
+            lines[object] = format!("object 500 500 {}", rest);
+        }));
+    }
+}
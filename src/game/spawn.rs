@@ -0,0 +1,378 @@
+#![allow(dead_code)]
+
+use rand::Rng;
+
+use game::object::*;
+use game::map::*;
+use game::rect::*;
+use game::draw_info::*;
+use game::monsters::*;
+use game::mapgen::*;
+use game::rng::GameRng;
+
+use game::is_blocked;
+
+//One step of a value that changes with depth.
+//The value holds from `depth` down until the next step takes over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub depth: i32,
+    pub value: u32,
+}
+
+//The value of the deepest step that has been reached. 0 above the first step.
+pub fn from_depth(steps: &[Step], depth: i32) -> u32 {
+    steps.iter()
+        .rev()
+        .find(|step| depth >= step.depth)
+        .map_or(0, |step| step.value)
+}
+
+//Parses "80" (80 from depth 1 on) or "1:20 3:30 5:40" (20 from depth 1, 30 from depth 3...).
+pub fn parse_steps(text: &str) -> Result<Vec<Step>, String> {
+    let mut steps: Vec<Step> = vec![];
+    for part in text.split_whitespace() {
+        let (depth, value) = match part.find(':') {
+            Some(colon) => (&part[..colon], &part[colon + 1..]),
+            None => ("1", part),
+        };
+        let step = match (depth.parse(), value.parse()) {
+            (Ok(depth), Ok(value)) => Step { depth: depth, value: value },
+            _ => return Err(format!("'{}' is not a number or <depth>:<number>", part)),
+        };
+        if let Some(last) = steps.last() {
+            if step.depth <= last.depth {
+                return Err(format!("depths have to go up, {} comes after {}", step.depth, last.depth));
+            }
+        }
+        steps.push(step);
+    }
+    if steps.is_empty() {
+        return Err("expected at least one number".into());
+    }
+    Ok(steps)
+}
+
+//Things to pick from at random, each with a weight that depends on depth.
+pub struct SpawnTable<T> {
+    entries: Vec<(T, Vec<Step>)>,
+}
+
+impl<T> SpawnTable<T> {
+    pub fn new() -> Self {
+        SpawnTable { entries: vec![] }
+    }
+
+    pub fn add(&mut self, value: T, weight: Vec<Step>) {
+        self.entries.push((value, weight));
+    }
+
+    //Picks an entry with a chance of its weight out of the total weight at this depth.
+    //None when nothing has any weight there.
+    pub fn choose(&self, depth: i32, rng: &mut GameRng) -> Option<&T> {
+        let total: u32 = self.entries.iter().map(|&(_, ref weight)| from_depth(weight, depth)).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut dice = rng.gen_range(0, total);
+        for &(ref value, ref weight) in &self.entries {
+            let weight = from_depth(weight, depth);
+            if dice < weight {
+                return Some(value);
+            }
+            dice -= weight;
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemKind {
+    HealingPotion,
+    LightningScroll,
+    Dagger,
+    Sword,
+    Shield,
+}
+
+impl ItemKind {
+    pub fn create(self, x: i32, y: i32) -> Object {
+        match self {
+            ItemKind::HealingPotion => {
+                let mut object = Object::new(x, y, ascii::healing_potion, *tileset::healing_potion, "healing potion", false);
+                object.item = Some(Item::Heal);
+                object
+            }
+            ItemKind::LightningScroll => {
+                let mut object = Object::new(x, y, ascii::scroll, *tileset::scroll, "scroll of lightning bolt", false);
+                object.item = Some(Item::Lightning);
+                object
+            }
+            ItemKind::Dagger => {
+                let mut object = Object::new(x, y, ascii::dagger, *tileset::dagger, "dagger", false);
+                object.item = Some(Item::Equipment);
+                object.equipment = Some(Equipment { slot: Slot::RightHand, equipped: false, power_bonus: 2, defense_bonus: 0 });
+                object
+            }
+            ItemKind::Sword => {
+                let mut object = Object::new(x, y, ascii::sword, *tileset::sword, "sword", false);
+                object.item = Some(Item::Equipment);
+                object.equipment = Some(Equipment { slot: Slot::RightHand, equipped: false, power_bonus: 3, defense_bonus: 0 });
+                object
+            }
+            ItemKind::Shield => {
+                let mut object = Object::new(x, y, ascii::shield, *tileset::shield, "shield", false);
+                object.item = Some(Item::Equipment);
+                object.equipment = Some(Equipment { slot: Slot::LeftHand, equipped: false, power_bonus: 0, defense_bonus: 1 });
+                object
+            }
+        }
+    }
+}
+
+//Potions are always around, the better gear gets more common further down.
+fn item_table() -> SpawnTable<ItemKind> {
+    let mut items = SpawnTable::new();
+    items.add(ItemKind::HealingPotion, vec![Step { depth: 1, value: 60 }]);
+    items.add(ItemKind::LightningScroll, vec![Step { depth: 1, value: 20 }, Step { depth: 4, value: 25 }]);
+    items.add(ItemKind::Dagger, vec![Step { depth: 1, value: 8 }]);
+    items.add(ItemKind::Sword, vec![Step { depth: 1, value: 6 }, Step { depth: 4, value: 10 }]);
+    items.add(ItemKind::Shield, vec![Step { depth: 1, value: 6 }, Step { depth: 6, value: 12 }]);
+    items
+}
+
+//How many things a room (for room based maps) or a whole level (for everything else) gets.
+//Rooms get a random amount up to the max, levels get exactly this many if there is space.
+const MAX_ROOM_MONSTERS: &'static [Step] = &[
+    Step { depth: 1, value: 3 }, Step { depth: 3, value: 4 }, Step { depth: 5, value: 5 }, Step { depth: 7, value: 6 },
+];
+const MAX_ROOM_ITEMS: &'static [Step] = &[Step { depth: 1, value: 2 }];
+const LEVEL_MONSTERS: &'static [Step] = &[
+    Step { depth: 1, value: 15 }, Step { depth: 2, value: 18 }, Step { depth: 3, value: 21 },
+    Step { depth: 4, value: 24 }, Step { depth: 5, value: 27 }, Step { depth: 6, value: 30 },
+];
+const LEVEL_ITEMS: &'static [Step] = &[Step { depth: 1, value: 8 }];
+
+//How many random spots are tried per thing before giving up on it
+const ATTEMPTS_PER_SPAWN: i32 = 10;
+
+//What every map generator uses to fill its levels.
+pub struct Spawner {
+    pub monsters: SpawnTable<MonsterTemplate>,
+    pub items: SpawnTable<ItemKind>,
+}
+
+impl Spawner {
+    pub fn new(monsters: &MonsterTable) -> Self {
+        let mut table = SpawnTable::new();
+        for template in &monsters.templates {
+            table.add(template.clone(), template.weight.clone());
+        }
+        Spawner {
+            monsters: table,
+            items: item_table(),
+        }
+    }
+
+    pub fn monster(&self, x: i32, y: i32, depth: i32, rng: &mut GameRng) -> Option<Object> {
+        self.monsters.choose(depth, rng).map(|template| template.spawn(x, y))
+    }
+
+    pub fn item(&self, x: i32, y: i32, depth: i32, rng: &mut GameRng) -> Option<Object> {
+        self.items.choose(depth, rng).map(|kind| kind.create(x, y))
+    }
+
+    //Fill a freshly generated map. The generator's monster / item spots are filled first,
+    //then maps with rooms get filled room by room and the rest all at once.
+    //Nothing that blocks is left standing on the player's start.
+    pub fn populate_generated(&self, generated: &GeneratedMap, objects: &mut Vec<Object>, rng: &mut GameRng, depth: i32) {
+        for &(x, y) in &generated.monster_spots {
+            if !is_blocked(x, y, &generated.map, objects) {
+                if let Some(monster) = self.monster(x, y, depth, rng) {
+                    objects.push(monster);
+                }
+            }
+        }
+        for &(x, y) in &generated.item_spots {
+            if let Some(item) = self.item(x, y, depth, rng) {
+                objects.push(item);
+            }
+        }
+
+        if generated.regions.is_empty() {
+            self.populate_level(&generated.map, &generated.spawn_points, objects, rng, depth);
+        } else {
+            for &room in &generated.regions {
+                self.populate_room(&generated.map, room, objects, rng, depth);
+            }
+        }
+
+        let start = generated.start;
+        objects.retain(|object| !(object.blocks && object.pos() == start));
+    }
+
+    //Fill a room of a room based map. The walls around the room are left alone.
+    pub fn populate_room(&self, map: &Map, room: Rect, objects: &mut Vec<Object>, rng: &mut GameRng, depth: i32) {
+        let inside = Rect { x1: room.x1 + 1, y1: room.y1 + 1, x2: room.x2, y2: room.y2 };
+        let monsters = rng.gen_range(0, from_depth(MAX_ROOM_MONSTERS, depth) as i32 + 1);
+        let items = rng.gen_range(0, from_depth(MAX_ROOM_ITEMS, depth) as i32 + 1);
+        self.populate(map, inside, monsters, items, objects, rng, depth);
+    }
+
+    //Fill a whole level at once, for maps that don't have rooms.
+    //Everything goes on one of the spots (the generator's spawn points) so nothing ends up
+    //somewhere the player can't get to, like a lake island.
+    pub fn populate_level(&self, map: &Map, spots: &[(i32, i32)], objects: &mut Vec<Object>, rng: &mut GameRng, depth: i32) {
+        if spots.is_empty() {
+            return;
+        }
+        let monsters = from_depth(LEVEL_MONSTERS, depth) as i32;
+        let items = from_depth(LEVEL_ITEMS, depth) as i32;
+
+        let mut placed = 0;
+        let mut attempts = 0;
+        while placed < monsters && attempts < monsters * ATTEMPTS_PER_SPAWN {
+            attempts += 1;
+            let (x, y) = *rng.choose(spots).unwrap();
+            if !is_blocked(x, y, map, objects) {
+                if let Some(monster) = self.monster(x, y, depth, rng) {
+                    objects.push(monster);
+                }
+                placed += 1;
+            }
+        }
+
+        let mut placed = 0;
+        let mut attempts = 0;
+        while placed < items && attempts < items * ATTEMPTS_PER_SPAWN {
+            attempts += 1;
+            let (x, y) = *rng.choose(spots).unwrap();
+            if !map.at(x, y).blocked {
+                if let Some(item) = self.item(x, y, depth, rng) {
+                    objects.push(item);
+                }
+                placed += 1;
+            }
+        }
+    }
+
+    //Places monsters on free floor and items on any floor (they don't block so they can end
+    //up under monsters) inside area, x2/y2 not included. Spots are picked at random so
+    //a cramped area can end up with less than asked for.
+    pub fn populate(&self, map: &Map, area: Rect, monsters: i32, items: i32,
+                    objects: &mut Vec<Object>, rng: &mut GameRng, depth: i32) {
+        if area.x2 <= area.x1 || area.y2 <= area.y1 {
+            return;
+        }
+
+        let mut placed = 0;
+        let mut attempts = 0;
+        while placed < monsters && attempts < monsters * ATTEMPTS_PER_SPAWN {
+            attempts += 1;
+            let x = rng.gen_range(area.x1, area.x2);
+            let y = rng.gen_range(area.y1, area.y2);
+            if !is_blocked(x, y, map, objects) {
+                if let Some(monster) = self.monster(x, y, depth, rng) {
+                    objects.push(monster);
+                }
+                placed += 1;
+            }
+        }
+
+        let mut placed = 0;
+        let mut attempts = 0;
+        while placed < items && attempts < items * ATTEMPTS_PER_SPAWN {
+            attempts += 1;
+            let x = rng.gen_range(area.x1, area.x2);
+            let y = rng.gen_range(area.y1, area.y2);
+            if !map.at(x, y).blocked {
+                if let Some(item) = self.item(x, y, depth, rng) {
+                    objects.push(item);
+                }
+                placed += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::rng;
+    use game::tile::*;
+
+    fn spawner() -> Spawner {
+        Spawner::new(&MonsterTable::shipped())
+    }
+
+    #[test]
+    fn levels_without_rooms_only_spawn_on_spawn_points() {
+        //a wall down the middle, only the left half can be reached from the start
+        let mut map = Map::new(20, 10, Tile::empty());
+        for y in 0 .. 10 {
+            map.set(5, y, Tile::wall());
+        }
+        let spawn_points: Vec<(i32, i32)> = (0 .. 10).flat_map(|y| (0 .. 5).map(move |x| (x, y))).collect();
+        let generated = GeneratedMap {
+            map: map,
+            start: (0, 0),
+            regions: vec![],
+            spawn_points: spawn_points,
+            monster_spots: vec![],
+            item_spots: vec![],
+        };
+
+        let mut objects = vec![];
+        spawner().populate_generated(&generated, &mut objects, &mut rng::from_seed(3), 3);
+        assert!(!objects.is_empty());
+        for object in &objects {
+            assert!(object.x < 5, "{} at {:?} can't be reached", object.name, object.pos());
+        }
+    }
+
+    const STEPS: &'static [Step] = &[Step { depth: 2, value: 10 }, Step { depth: 4, value: 30 }];
+
+    #[test]
+    fn from_depth_uses_the_deepest_step_reached() {
+        assert_eq!(from_depth(STEPS, 1), 0);
+        assert_eq!(from_depth(STEPS, 2), 10);
+        assert_eq!(from_depth(STEPS, 3), 10);
+        assert_eq!(from_depth(STEPS, 4), 30);
+        assert_eq!(from_depth(STEPS, 50), 30);
+        assert_eq!(from_depth(&[], 3), 0);
+    }
+
+    #[test]
+    fn parses_steps() {
+        assert_eq!(parse_steps("80"), Ok(vec![Step { depth: 1, value: 80 }]));
+        assert_eq!(parse_steps(" 2:10  4:30 "), Ok(STEPS.to_vec()));
+    }
+
+    #[test]
+    fn steps_have_to_go_deeper() {
+        assert!(parse_steps("3:10 2:20").is_err());
+        assert!(parse_steps("2:10 2:20").is_err());
+        assert!(parse_steps("50 1:20").is_err());
+    }
+
+    #[test]
+    fn steps_are_numbers() {
+        assert!(parse_steps("lots").is_err());
+        assert!(parse_steps("1:lots").is_err());
+        assert!(parse_steps("one:10").is_err());
+        assert!(parse_steps("1:-5").is_err());
+        assert!(parse_steps("").is_err());
+    }
+
+    #[test]
+    fn nothing_to_choose_with_zero_weights() {
+        let mut table = SpawnTable::new();
+        table.add("ghost", vec![Step { depth: 1, value: 0 }]);
+        table.add("bat", vec![Step { depth: 1, value: 0 }, Step { depth: 3, value: 5 }]);
+        let mut rng = rng::from_seed(1);
+        assert_eq!(table.choose(1, &mut rng), None);
+        assert_eq!(table.choose(3, &mut rng), Some(&"bat"));
+        assert_eq!(SpawnTable::<&str>::new().choose(1, &mut rng), None);
+    }
+}
@@ -0,0 +1,869 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+use std::mem;
+
+use rand::Rng;
+
+use tcod::colors;
+
+use game::*;
+use game::object::*;
+use game::map::*;
+use game::tile::*;
+use game::draw_info::*;
+use game::rng::{self, GameRng};
+use game::fov::{self, FovAlgorithm, Visibility};
+use game::terrain::*;
+use game::messages::*;
+use game::monsters::*;
+use game::spawn::*;
+use game::mapgen::*;
+
+const FOV_ALGO: FovAlgorithm = FovAlgorithm::Shadowcasting;
+const FOV_LIGHT_WALLS: bool = true;
+const TORCH_RADIUS: i32 = 10;
+
+pub const MAX_INVENTORY: usize = 26;
+const HEAL_AMOUNT: i32 = 4;
+const LIGHTNING_DAMAGE: i32 = 20;
+const LIGHTNING_RANGE: i32 = 5;
+//resting stops after this many turns even if nothing showed up
+const MAX_REST_TURNS: i32 = 100;
+
+const MAP_WIDTH: i32 = 80;
+const MAP_HEIGHT: i32 = 45;
+
+//Something the player wants to do this turn.
+//This is what input (keyboard, bots, tests) gets translated into before touching the game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Move(i32, i32),
+    //let a turn go by without doing anything
+    Wait,
+    //keep waiting until a monster shows up (or the player gets hurt)
+    Rest,
+    //take the stairs the player is standing on
+    Descend,
+    Ascend,
+    //pick up whatever item the player is standing on
+    PickUp,
+    //use / drop the item at this index of the inventory
+    UseItem(usize),
+    DropItem(usize),
+}
+
+//What happened when an item was used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UseResult {
+    UsedUp,
+    //still in the inventory afterwards, e.g. equipment
+    Kept,
+    Cancelled,
+}
+
+//A dungeon level that the player isn't currently on.
+//Kept around so going back up the stairs puts you back where you were.
+pub struct Level {
+    pub map: Map,
+    //everything on the level except the player
+    pub objects: Vec<Object>,
+}
+
+//Everything needed to simulate the game without a window.
+//run() owns one of these and just feeds it actions and renders it, so anything
+//that can build Actions can drive the game. (tests, bots, scripts)
+pub struct GameState {
+    pub map: Map,
+    pub objects: Vec<Object>,
+    //the items the player is carrying. At most MAX_INVENTORY of them
+    pub inventory: Vec<Object>,
+    pub messages: Messages,
+    pub tick: i32,
+    //1 is the top of the dungeon
+    pub depth: i32,
+    //every level the player has been to other than the current one, by depth
+    pub levels: HashMap<i32, Level>,
+    pub visible: Visibility,
+    //what fov and pathfinding look at. Follows the map through sync_terrain()
+    pub terrain: TerrainGrid,
+    pub rng: GameRng,
+    //what new levels get filled with. The monsters come from the monster data file
+    pub spawner: Spawner,
+    //builds every new level the player walks into
+    pub generator: Box<dyn MapGenerator>,
+    //where the player was the last time fov was computed
+    //so we only recompute when they actually moved
+    fov_player_location: (i32, i32),
+}
+
+impl GameState {
+    pub fn new(seed: u32, monsters: MonsterTable, generator: Box<dyn MapGenerator>) -> Self {
+        let mut rng = rng::from_seed(seed);
+
+        let mut player = Object::new(0, 0, ascii::player, *tileset::player, "player",true);
+        player.alive = true;
+        player.fighter = Some(Fighter::new(30, 2, 5, 0, DeathCallback::Player));
+
+        let spawner = Spawner::new(&monsters);
+        let (map, spawned, start) = generate_level(1, &*generator, &spawner, &mut rng);
+        player.set_pos_tup(start);
+
+        let mut objects = vec!(player);
+        objects.extend(spawned);
+
+        let mut state = GameState::with_rng(map, objects, vec![], Messages::new(), 0, 1, HashMap::new(), spawner, generator, rng);
+        // a warm welcoming message!
+        state.messages.add("Welcome stranger! Prepare to perish in the Tombs of the Ancient Kings.", colors::RED);
+        state
+    }
+
+    //Rebuild a game from data that was created somewhere else (e.g. a save file).
+    //The fov is derived from the map so it doesn't need to be stored.
+    //The rng isn't part of a save so a loaded game gets a fresh random one.
+    pub fn from_parts(map: Map, objects: Vec<Object>, inventory: Vec<Object>, messages: Messages,
+                      tick: i32, depth: i32, levels: HashMap<i32, Level>,
+                      monsters: MonsterTable, generator: Box<dyn MapGenerator>) -> Self {
+        GameState::with_rng(map, objects, inventory, messages, tick, depth, levels, Spawner::new(&monsters),
+                            generator, rng::from_seed(rng::random_seed()))
+    }
+
+    fn with_rng(mut map: Map, objects: Vec<Object>, inventory: Vec<Object>, mut messages: Messages,
+                tick: i32, depth: i32, levels: HashMap<i32, Level>, spawner: Spawner,
+                generator: Box<dyn MapGenerator>, rng: GameRng) -> Self {
+        let visible = Visibility::empty(map.width(), map.height());
+        //the grid starts out matching the whole map so earlier changes don't matter
+        map.take_changes();
+        let terrain = TerrainGrid::new(&map);
+        messages.turn = tick;
+
+        let mut state = GameState {
+            map: map,
+            objects: objects,
+            inventory: inventory,
+            messages: messages,
+            tick: tick,
+            depth: depth,
+            levels: levels,
+            visible: visible,
+            terrain: terrain,
+            rng: rng,
+            spawner: spawner,
+            generator: generator,
+            //start at a impossible value so the first update_fov always computes something
+            fov_player_location: (-1, -1),
+        };
+        //compute the fov before the first tick so the user can see something.
+        state.update_fov();
+        state
+    }
+
+    pub fn player(&self) -> &Object {
+        &self.objects[PLAYER]
+    }
+
+    //Once the player is dead there is nothing left to do but look at the corpse.
+    pub fn is_game_over(&self) -> bool {
+        !self.objects[PLAYER].alive
+    }
+
+    pub fn is_in_fov(&self, x: i32, y: i32) -> bool {
+        self.visible.is_visible(x, y)
+    }
+
+    //Advance the game by one player action.
+    //The player acts first, then every monster gets their turn if the player used theirs.
+    pub fn step(&mut self, action: Action) -> PlayerAction {
+        if self.is_game_over() {
+            return PlayerAction::DidntTakeTurn;
+        }
+        //resting is just a lot of waits so it gets to go through step() for each of them
+        if action == Action::Rest {
+            return self.rest();
+        }
+
+        let player_action = match action {
+            Action::Move(dx, dy) => {
+                player_move_or_attack(dx, dy, &mut self.map, &mut self.objects, &mut self.messages);
+                PlayerAction::TookTurn
+            }
+            Action::Wait | Action::Rest => PlayerAction::TookTurn,
+            Action::Descend => self.take_stairs(Stairs::Down),
+            Action::Ascend => self.take_stairs(Stairs::Up),
+            Action::PickUp => self.pick_item_up(),
+            Action::UseItem(index) => self.use_item(index),
+            Action::DropItem(index) => self.drop_item(index),
+        };
+        //monsters should path around whatever the player just changed,
+        //and see the player from where they are now
+        self.sync_terrain();
+        self.update_fov();
+
+        // let monstars take their turn
+        if player_action == PlayerAction::TookTurn {
+            for id in PLAYER + 1 .. self.objects.len() {
+                if self.is_game_over() {
+                    break;
+                }
+                //take the ai out while it runs so it can borrow the rest of the state
+                if let Some(mut ai) = self.objects[id].ai.take() {
+                    ai.take_turn(id, self);
+                    if self.objects[id].alive {
+                        self.objects[id].ai = Some(ai);
+                    }
+                }
+            }
+            //the monsters might have opened doors, catch the fov up before telling the player what burns
+            self.sync_terrain();
+            self.update_fov();
+            self.hurt_by_tiles();
+            self.tick += 1;
+            self.messages.turn = self.tick;
+        }
+
+        self.sync_terrain();
+        self.update_fov();
+        player_action
+    }
+
+    //Everything still standing in something like lava at the end of the turn gets hurt by it.
+    //The player only hears about the ones they can see.
+    fn hurt_by_tiles(&mut self) {
+        for id in 0 .. self.objects.len() {
+            let (x, y) = self.objects[id].pos();
+            if !self.objects[id].alive || self.objects[id].fighter.is_none() {
+                continue;
+            }
+            let kind = self.map.at(x, y).kind;
+            let damage = kind.damage();
+            if damage == 0 {
+                continue;
+            }
+
+            if id == PLAYER || self.is_in_fov(x, y) {
+                self.messages.add(format!("The {} burns {} for {} hit points.", kind.name(), self.objects[id].name, damage), colors::ORANGE);
+                self.objects[id].take_damage(damage, &mut self.messages);
+            } else {
+                //it still dies out of sight, just without telling anyone
+                self.objects[id].take_damage(damage, &mut Messages::new());
+            }
+        }
+    }
+
+    fn rest(&mut self) -> PlayerAction {
+        if let Some(id) = self.monster_in_fov() {
+            let message = format!("You can't rest with the {} right there!", self.objects[id].name);
+            self.messages.add(message, colors::RED);
+            return PlayerAction::DidntTakeTurn;
+        }
+
+        let start_hp = self.objects[PLAYER].fighter.map_or(0, |f| f.hp);
+        let mut turns = 0;
+        while turns < MAX_REST_TURNS {
+            self.step(Action::Wait);
+            turns += 1;
+
+            if self.is_game_over() {
+                return PlayerAction::TookTurn;
+            }
+            if let Some(id) = self.monster_in_fov() {
+                let message = format!("You stop resting, a {} comes into view.", self.objects[id].name);
+                self.messages.add(message, colors::LIGHT_RED);
+                return PlayerAction::TookTurn;
+            }
+            if self.objects[PLAYER].fighter.map_or(0, |f| f.hp) < start_hp {
+                self.messages.add("You stop resting, something hurt you!", colors::LIGHT_RED);
+                return PlayerAction::TookTurn;
+            }
+        }
+        self.messages.add(format!("You rest for {} turns.", turns), colors::LIGHT_GREY);
+        PlayerAction::TookTurn
+    }
+
+    //any living monster the player can currently see
+    fn monster_in_fov(&self) -> Option<usize> {
+        self.objects.iter().enumerate().position(|(id, object)| {
+            id != PLAYER && object.fighter.is_some() && object.ai.is_some() && self.is_in_fov(object.x, object.y)
+        })
+    }
+
+    //The monsters on the old level don't get a turn since the player is already gone.
+    //Everyone on the new level does, the player just walked in on them after all.
+    fn take_stairs(&mut self, direction: Stairs) -> PlayerAction {
+        let player_position = self.objects[PLAYER].pos();
+        let on_stairs = self.objects.iter().any(|object| {
+            object.stairs == Some(direction) && object.pos() == player_position
+        });
+        if !on_stairs {
+            let message = match direction {
+                Stairs::Down => "There are no stairs going down here.",
+                Stairs::Up => "There are no stairs going up here.",
+            };
+            self.messages.add(message, colors::WHITE);
+            return PlayerAction::DidntTakeTurn;
+        }
+
+        let new_depth = match direction {
+            Stairs::Down => self.depth + 1,
+            Stairs::Up => self.depth - 1,
+        };
+
+        let (map, objects, arrival) = match self.levels.remove(&new_depth) {
+            Some(level) => {
+                //come out of the staircase that leads back to where we just were
+                let back = match direction {
+                    Stairs::Down => Stairs::Up,
+                    Stairs::Up => Stairs::Down,
+                };
+                let arrival = level.objects.iter()
+                    .find(|object| object.stairs == Some(back))
+                    .map(|object| object.pos())
+                    .expect("load_game() checks that stored levels have stairs leading back");
+                (level.map, level.objects, arrival)
+            }
+            None => generate_level(new_depth, &*self.generator, &self.spawner, &mut self.rng),
+        };
+
+        //stash the level we are leaving and swap the new one in
+        let old_map = mem::replace(&mut self.map, map);
+        let old_objects = self.objects.split_off(PLAYER + 1);
+        self.levels.insert(self.depth, Level { map: old_map, objects: old_objects });
+
+        self.objects.extend(objects);
+        self.objects[PLAYER].set_pos_tup(arrival);
+        self.depth = new_depth;
+
+        self.map.take_changes();
+        self.terrain = TerrainGrid::new(&self.map);
+        //the monsters down here act right after this, they have to see the new fov and not the old level's
+        //the monsters down here act right after this, they have to see the new fov and not the old level's
+        self.fov_player_location = (-1, -1);
+        self.update_fov();
+
+        let message = match direction {
+            Stairs::Down => format!("You descend deeper into the dungeon to depth {}.", new_depth),
+            Stairs::Up => format!("You climb back up to depth {}.", new_depth),
+        };
+        self.messages.add(message, colors::VIOLET);
+        PlayerAction::TookTurn
+    }
+
+    /// add to the player's inventory and remove from the map
+    fn pick_item_up(&mut self) -> PlayerAction {
+        let player_position = self.objects[PLAYER].pos();
+        let item_id = self.objects.iter().position(|object| {
+            object.item.is_some() && object.pos() == player_position
+        });
+        let item_id = match item_id {
+            Some(item_id) => item_id,
+            None => {
+                self.messages.add("There is nothing here to pick up.", colors::WHITE);
+                return PlayerAction::DidntTakeTurn;
+            }
+        };
+
+        if self.inventory.len() >= MAX_INVENTORY {
+            let message = format!("Your inventory is full, cannot pick up {}.", self.objects[item_id].name);
+            self.messages.add(message, colors::RED);
+            return PlayerAction::DidntTakeTurn;
+        }
+
+        let item = self.objects.swap_remove(item_id);
+        self.messages.add(format!("You picked up a {}!", item.name), colors::GREEN);
+        self.inventory.push(item);
+        PlayerAction::TookTurn
+    }
+
+    fn use_item(&mut self, inventory_id: usize) -> PlayerAction {
+        let item = match self.inventory.get(inventory_id).and_then(|object| object.item) {
+            Some(item) => item,
+            None => {
+                self.messages.add("That can't be used.", colors::WHITE);
+                return PlayerAction::DidntTakeTurn;
+            }
+        };
+
+        let result = match item {
+            Item::Heal => self.cast_heal(),
+            Item::Lightning => self.cast_lightning(),
+            Item::Equipment => self.toggle_equipment(inventory_id),
+        };
+
+        match result {
+            UseResult::UsedUp => {
+                // destroy after use
+                self.inventory.remove(inventory_id);
+                PlayerAction::TookTurn
+            }
+            UseResult::Kept => PlayerAction::TookTurn,
+            UseResult::Cancelled => PlayerAction::DidntTakeTurn,
+        }
+    }
+
+    fn drop_item(&mut self, inventory_id: usize) -> PlayerAction {
+        if inventory_id >= self.inventory.len() {
+            return PlayerAction::DidntTakeTurn;
+        }
+        if self.inventory[inventory_id].equipment.map_or(false, |e| e.equipped) {
+            self.dequip(inventory_id);
+        }
+
+        let mut item = self.inventory.remove(inventory_id);
+        item.set_pos_tup(self.objects[PLAYER].pos());
+        self.messages.add(format!("You dropped a {}.", item.name), colors::YELLOW);
+        self.objects.push(item);
+        PlayerAction::TookTurn
+    }
+
+    fn cast_heal(&mut self) -> UseResult {
+        // heal the player
+        let player = &mut self.objects[PLAYER];
+        if let Some(fighter) = player.fighter {
+            if fighter.hp == fighter.max_hp {
+                self.messages.add("You are already at full health.", colors::RED);
+                return UseResult::Cancelled;
+            }
+            self.messages.add("Your wounds start to feel better!", colors::LIGHT_VIOLET);
+            player.heal(HEAL_AMOUNT);
+            return UseResult::UsedUp;
+        }
+        UseResult::Cancelled
+    }
+
+    fn cast_lightning(&mut self) -> UseResult {
+        // find closest enemy (inside a maximum range) and damage it
+        let monster_id = self.closest_monster(LIGHTNING_RANGE);
+        if let Some(monster_id) = monster_id {
+            // zap it!
+            let message = format!("A lightning bolt strikes the {} with a loud thunder! The damage is {} hit points.",
+                self.objects[monster_id].name, LIGHTNING_DAMAGE);
+            self.messages.add(message, colors::LIGHT_BLUE);
+            if let Some(xp) = self.objects[monster_id].take_damage(LIGHTNING_DAMAGE, &mut self.messages) {
+                if let Some(ref mut fighter) = self.objects[PLAYER].fighter {
+                    fighter.xp += xp;
+                }
+            }
+            UseResult::UsedUp
+        } else {
+            // no enemy found within maximum range
+            self.messages.add("No enemy is close enough to strike.", colors::RED);
+            UseResult::Cancelled
+        }
+    }
+
+    /// find closest enemy, up to a maximum range, and in the player's FOV
+    fn closest_monster(&self, max_range: i32) -> Option<usize> {
+        let mut closest_enemy = None;
+        let mut closest_dist = (max_range + 1) as f32; // start with (slightly more than) maximum range
+
+        for (id, object) in self.objects.iter().enumerate() {
+            if id != PLAYER && object.fighter.is_some() && object.ai.is_some() && self.is_in_fov(object.x, object.y) {
+                // calculate distance between this object and the player
+                let dist = self.objects[PLAYER].distance_to(object);
+                if dist < closest_dist {
+                    // it's closer, so remember it
+                    closest_enemy = Some(id);
+                    closest_dist = dist;
+                }
+            }
+        }
+        closest_enemy
+    }
+
+    fn toggle_equipment(&mut self, inventory_id: usize) -> UseResult {
+        let equipment = match self.inventory[inventory_id].equipment {
+            Some(equipment) => equipment,
+            None => return UseResult::Cancelled,
+        };
+        if equipment.equipped {
+            self.dequip(inventory_id);
+        } else {
+            // if the slot is already being used, dequip whatever is there first
+            let current = self.inventory.iter().position(|item| {
+                item.equipment.map_or(false, |e| e.equipped && e.slot == equipment.slot)
+            });
+            if let Some(current) = current {
+                self.dequip(current);
+            }
+            self.equip(inventory_id);
+        }
+        UseResult::Kept
+    }
+
+    fn equip(&mut self, inventory_id: usize) {
+        let item = &mut self.inventory[inventory_id];
+        if let Some(ref mut equipment) = item.equipment {
+            equipment.equipped = true;
+            if let Some(ref mut fighter) = self.objects[PLAYER].fighter {
+                fighter.power += equipment.power_bonus;
+                fighter.defense += equipment.defense_bonus;
+            }
+            self.messages.add(format!("Equipped {} on {}.", item.name, equipment.slot.name()), colors::LIGHT_GREEN);
+        }
+    }
+
+    fn dequip(&mut self, inventory_id: usize) {
+        let item = &mut self.inventory[inventory_id];
+        if let Some(ref mut equipment) = item.equipment {
+            equipment.equipped = false;
+            if let Some(ref mut fighter) = self.objects[PLAYER].fighter {
+                fighter.power -= equipment.power_bonus;
+                fighter.defense -= equipment.defense_bonus;
+            }
+            self.messages.add(format!("Dequipped {} from {}.", item.name, equipment.slot.name()), colors::LIGHT_YELLOW);
+        }
+    }
+
+    //Bring the terrain grid up to date with any tiles that changed on the map.
+    //If something now blocks sight diffrently the fov has to be recomputed even if the player didn't move.
+    pub fn sync_terrain(&mut self) {
+        if self.terrain.sync(&mut self.map) {
+            self.fov_player_location = (-1, -1);
+        }
+    }
+
+    //Update fov / explored cells
+    pub fn update_fov(&mut self) {
+        let player_location = self.objects[PLAYER].pos();
+        if player_location == self.fov_player_location {
+            return;
+        }
+        self.fov_player_location = player_location;
+
+        let (x, y) = player_location;
+        self.visible = fov::compute_fov(&self.terrain, x, y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
+
+        for x in 0 .. self.map.width() {
+            for y in 0 .. self.map.height() {
+                if self.visible.is_visible(x, y) {
+                    // since it's visible, explore it
+                    self.map.set_explored(x,y);
+                }
+            }
+        }
+    }
+}
+
+//Build a brand new level for the given depth.
+//Returns the map, everything on it and where the player should arrive.
+//Every level has stairs going down, and every level below the first has stairs
+//going back up which is where the player starts.
+fn generate_level(depth: i32, generator: &dyn MapGenerator, spawner: &Spawner, rng: &mut GameRng) -> (Map, Vec<Object>, (i32, i32)) {
+    let generated = generator.generate(MAP_WIDTH, MAP_HEIGHT, rng);
+    let mut objects = vec![];
+    spawner.populate_generated(&generated, &mut objects, rng, depth);
+    let arrival = generated.start;
+    let mut map = generated.map;
+
+    //the stairs down go on one of the generator's spawn points, just not right where the player arrives
+    let spots: Vec<_> = generated.spawn_points.iter()
+        .cloned()
+        .filter(|&(x, y)| (x, y) != arrival && !is_blocked(x, y, &map, &objects))
+        .collect();
+    let (x, y) = match rng.choose(&spots) {
+        Some(&spot) => spot,
+        None => map.random_open_position(&objects, rng).expect("level has no floor for the stairs"),
+    };
+    let mut stairs_down = Object::new(x, y, ascii::stairs_down, *tileset::stairs_down, "stairs down", false);
+    stairs_down.always_visible = true;
+    stairs_down.stairs = Some(Stairs::Down);
+    objects.push(stairs_down);
+    //the stairs object is what gets used, the tile is so the map itself shows where they are
+    map.set_kind(x, y, TileKind::StairsDown);
+
+    if depth > 1 {
+        let (x, y) = arrival;
+        let mut stairs_up = Object::new(x, y, ascii::stairs_up, *tileset::stairs_up, "stairs up", false);
+        stairs_up.always_visible = true;
+        stairs_up.stairs = Some(Stairs::Up);
+        objects.push(stairs_up);
+        map.set_kind(x, y, TileKind::StairsUp);
+    }
+
+    (map, objects, arrival)
+}
+
+//A caves game from a fixed seed with the shipped monsters, for the tests.
+#[cfg(test)]
+impl GameState {
+    pub fn test_game() -> GameState {
+        GameState::new(7, MonsterTable::shipped(), generator_from_name("caves").unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ai::Ai;
+
+    //a fresh game with only the player in it, so nothing else gets in the way.
+    //the player stands at (5, 5) in the middle of some floor so the tests don't depend on the map
+    fn new_game() -> GameState {
+        let mut state = GameState::test_game();
+        state.objects.truncate(PLAYER + 1);
+        for y in 4 .. 7 {
+            for x in 4 .. 7 {
+                state.map.set(x, y, Tile::empty());
+            }
+        }
+        state.objects[PLAYER].set_pos(5, 5);
+        state
+    }
+
+    //puts the tile right east of the player and returns where that is
+    fn set_east(state: &mut GameState, tile: Tile) -> (i32, i32) {
+        let (x, y) = state.objects[PLAYER].pos();
+        state.map.set(x + 1, y, tile);
+        (x + 1, y)
+    }
+
+    #[test]
+    fn moving_onto_floor() {
+        let mut state = new_game();
+        let east = set_east(&mut state, Tile::empty());
+        assert_eq!(state.step(Action::Move(1, 0)), PlayerAction::TookTurn);
+        assert_eq!(state.objects[PLAYER].pos(), east);
+        assert_eq!(state.tick, 1);
+    }
+
+    #[test]
+    fn walls_stop_the_player() {
+        let mut state = new_game();
+        let start = state.objects[PLAYER].pos();
+        set_east(&mut state, Tile::wall());
+        //bumping into a wall still uses up the turn
+        assert_eq!(state.step(Action::Move(1, 0)), PlayerAction::TookTurn);
+        assert_eq!(state.objects[PLAYER].pos(), start);
+        assert_eq!(state.tick, 1);
+    }
+
+    #[test]
+    fn attacking_instead_of_moving() {
+        let mut state = new_game();
+        let start = state.objects[PLAYER].pos();
+        let (x, y) = set_east(&mut state, Tile::empty());
+        let mut target = Object::new(x, y, ascii::player, *tileset::player, "target", true);
+        target.alive = true;
+        target.fighter = Some(Fighter::new(100, 0, 0, 0, DeathCallback::Monster));
+        state.objects.push(target);
+
+        state.step(Action::Move(1, 0));
+        assert_eq!(state.objects[PLAYER].pos(), start);
+        assert_eq!(state.objects[1].pos(), (x, y));
+        assert!(state.objects[1].fighter.unwrap().hp < 100);
+    }
+
+    #[test]
+    fn every_turn_advances_the_tick() {
+        let mut state = new_game();
+        set_east(&mut state, Tile::wall());
+        for _ in 0 .. 3 {
+            state.step(Action::Move(1, 0));
+        }
+        assert_eq!(state.tick, 3);
+    }
+
+    #[test]
+    fn waiting_advances_the_tick() {
+        let mut state = new_game();
+        let start = state.objects[PLAYER].pos();
+        assert_eq!(state.step(Action::Wait), PlayerAction::TookTurn);
+        assert_eq!(state.step(Action::Wait), PlayerAction::TookTurn);
+        assert_eq!(state.objects[PLAYER].pos(), start);
+        assert_eq!(state.tick, 2);
+    }
+
+    #[test]
+    fn the_dead_dont_move() {
+        let mut state = new_game();
+        let start = state.objects[PLAYER].pos();
+        set_east(&mut state, Tile::empty());
+        state.objects[PLAYER].alive = false;
+        assert_eq!(state.step(Action::Move(1, 0)), PlayerAction::DidntTakeTurn);
+        assert_eq!(state.objects[PLAYER].pos(), start);
+        assert_eq!(state.tick, 0);
+    }
+
+    //a room split in two by a wall with a closed door at (10, 3), the player right in front of it
+    fn door_level(others: Vec<Object>) -> GameState {
+        let mut map = Map::new(30, 7, Tile::wall());
+        for y in 1 .. 6 {
+            for x in 1 .. 29 {
+                map.set(x, y, if x == 10 { Tile::wall() } else { Tile::empty() });
+            }
+        }
+        map.set(10, 3, Tile::new(TileKind::ClosedDoor));
+
+        let mut player = Object::new(9, 3, ascii::player, *tileset::player, "player", true);
+        player.alive = true;
+        player.fighter = Some(Fighter::new(30, 2, 5, 0, DeathCallback::Player));
+        let mut objects = vec![player];
+        objects.extend(others);
+
+        GameState::from_parts(map, objects, vec![], Messages::new(), 0, 1, HashMap::new(),
+                              MonsterTable::shipped(), generator_from_name("rooms").unwrap())
+    }
+
+    //an orc that just stands there
+    fn orc(state: &GameState, x: i32, y: i32) -> Object {
+        let mut orc = state.spawner.monsters.choose(1, &mut rng::from_seed(1)).unwrap().spawn(x, y);
+        orc.ai = None;
+        orc
+    }
+
+    fn messages(state: &GameState) -> Vec<String> {
+        state.messages.iter().map(|message| message.text.clone()).collect()
+    }
+
+    //walks east every turn no matter what it can see
+    #[derive(Debug)]
+    struct WalkEast;
+
+    impl Ai for WalkEast {
+        fn name(&self) -> &'static str {
+            "walk east"
+        }
+
+        fn take_turn(&mut self, monster_id: usize, state: &mut GameState) {
+            move_by(monster_id, 1, 0, &mut state.map, &mut state.objects);
+        }
+    }
+
+    //the door is open and an orc east of it is about to walk into lava at (12, 3),
+    //which can be seen from right in front of the door but not from the tile above
+    fn lava_behind_the_door(player_y: i32) -> GameState {
+        let state = door_level(vec![]);
+        let mut walker = orc(&state, 11, 3);
+        walker.ai = Some(Box::new(WalkEast));
+        let mut state = door_level(vec![walker]);
+        state.objects[PLAYER].set_pos(9, player_y);
+        state.map.set_kind(10, 3, TileKind::OpenDoor);
+        state.map.set_kind(12, 3, TileKind::Lava);
+        state.sync_terrain();
+        state.update_fov();
+        state
+    }
+
+    fn burns(state: &GameState) -> usize {
+        messages(state).iter().filter(|text| text.contains("burns")).count()
+    }
+
+    #[test]
+    fn bumping_a_door_opens_it() {
+        let mut state = door_level(vec![]);
+        assert!(!state.is_in_fov(11, 3));
+
+        state.step(Action::Move(1, 0));
+        assert_eq!(state.map.at(10, 3).kind, TileKind::OpenDoor);
+        assert_eq!(state.objects[PLAYER].pos(), (9, 3));
+        assert_eq!(messages(&state), vec!["player opens the closed door."]);
+        //the terrain and fov pick up the open door right away
+        assert!(state.is_in_fov(11, 3));
+
+        state.step(Action::Move(1, 0));
+        assert_eq!(state.objects[PLAYER].pos(), (10, 3));
+    }
+
+    #[test]
+    fn only_hears_about_burning_monsters_in_view() {
+        let state = door_level(vec![]);
+        let seen = orc(&state, 5, 3);
+        let unseen = orc(&state, 20, 3);
+        let mut state = door_level(vec![seen, unseen]);
+        state.map.set_kind(5, 3, TileKind::Lava);
+        state.map.set_kind(20, 3, TileKind::Lava);
+        state.sync_terrain();
+        state.update_fov();
+
+        state.step(Action::Wait);
+        let hp = |state: &GameState, id: usize| state.objects[id].fighter.unwrap().hp;
+        assert_eq!(hp(&state, 1), hp(&state, 2));
+        assert!(hp(&state, 2) < state.objects[2].fighter.unwrap().max_hp);
+        let burns: Vec<String> = messages(&state).into_iter().filter(|text| text.contains("burns")).collect();
+        assert_eq!(burns.len(), 1);
+        assert!(burns[0].contains(&state.objects[1].name));
+    }
+
+    #[test]
+    fn monsters_below_see_the_player_arrive() {
+        //the player walks over to stairs on the far side of the door where nothing below can be seen from
+        let mut state = door_level(vec![]);
+        state.objects[PLAYER].set_pos(25, 3);
+        let mut stairs_down = Object::new(25, 3, ascii::stairs_down, *tileset::stairs_down, "stairs down", false);
+        stairs_down.stairs = Some(Stairs::Down);
+        state.objects.push(stairs_down);
+        state.update_fov();
+
+        //and comes out right next to an orc that is awake
+        let mut below = Map::new(10, 7, Tile::wall());
+        for y in 1 .. 6 {
+            for x in 1 .. 9 {
+                below.set(x, y, Tile::empty());
+            }
+        }
+        let mut stairs_up = Object::new(2, 3, ascii::stairs_up, *tileset::stairs_up, "stairs up", false);
+        stairs_up.stairs = Some(Stairs::Up);
+        let awake = state.spawner.monsters.choose(1, &mut rng::from_seed(1)).unwrap().spawn(3, 3);
+        assert!(awake.ai.is_some());
+        state.levels.insert(2, Level { map: below, objects: vec![stairs_up, awake] });
+
+        assert_eq!(state.step(Action::Descend), PlayerAction::TookTurn);
+        assert_eq!(state.depth, 2);
+        assert!(state.is_in_fov(3, 3));
+        let player = state.objects[PLAYER].fighter.unwrap();
+        assert!(player.hp < player.max_hp);
+    }
+
+    #[test]
+    fn no_news_of_monsters_burning_where_the_player_just_stopped_looking() {
+        let mut state = lava_behind_the_door(3);
+        assert!(state.is_in_fov(12, 3));
+
+        state.step(Action::Move(0, -1));
+        assert_eq!(state.objects[1].pos(), (12, 3));
+        assert!(state.objects[1].fighter.unwrap().hp < state.objects[1].fighter.unwrap().max_hp);
+        assert!(!state.is_in_fov(12, 3));
+        assert_eq!(burns(&state), 0);
+    }
+
+    #[test]
+    fn hears_about_monsters_burning_where_the_player_just_started_looking() {
+        let mut state = lava_behind_the_door(2);
+        assert!(!state.is_in_fov(12, 3));
+
+        state.step(Action::Move(0, 1));
+        assert_eq!(state.objects[1].pos(), (12, 3));
+        assert!(state.is_in_fov(12, 3));
+        assert_eq!(burns(&state), 1);
+    }
+
+    fn seeded(seed: u32) -> GameState {
+        GameState::new(seed, MonsterTable::shipped(), generator_from_name("rooms").unwrap())
+    }
+
+    fn kinds(state: &GameState) -> Vec<TileKind> {
+        let mut kinds = vec![];
+        for y in 0 .. state.map.height() {
+            for x in 0 .. state.map.width() {
+                kinds.push(state.map.at(x, y).kind);
+            }
+        }
+        kinds
+    }
+
+    fn placed(state: &GameState) -> Vec<(String, (i32, i32))> {
+        state.objects.iter().map(|object| (object.name.clone(), object.pos())).collect()
+    }
+
+    #[test]
+    fn the_same_seed_makes_the_same_game() {
+        let (first, second) = (seeded(1234), seeded(1234));
+        assert_eq!(kinds(&first), kinds(&second));
+        assert_eq!(first.objects[PLAYER].pos(), second.objects[PLAYER].pos());
+        assert_eq!(placed(&first), placed(&second));
+    }
+
+    #[test]
+    fn different_seeds_make_different_maps() {
+        assert!(kinds(&seeded(1)) != kinds(&seeded(2)));
+    }
+}
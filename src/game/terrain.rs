@@ -0,0 +1,134 @@
+use game::map::*;
+
+//The questions fov and pathfinding ask about a map.
+//Both Map and TerrainGrid can answer them so the algorithms work on either.
+pub trait Terrain {
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    //can you see through (x, y)? out of bounds is never transparent.
+    fn is_transparent(&self, x: i32, y: i32) -> bool;
+    //can you walk onto (x, y)? out of bounds is never walkable.
+    fn is_walkable(&self, x: i32, y: i32) -> bool;
+    //extra pathfinding cost of walking onto (x, y), 10 per tile. see TileKind::move_cost()
+    fn move_cost(&self, _x: i32, _y: i32) -> u32 {
+        0
+    }
+    //is (x, y) blocked but can be opened by walking into it? (closed doors)
+    fn is_openable(&self, _x: i32, _y: i32) -> bool {
+        false
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width() && y < self.height()
+    }
+}
+
+impl Terrain for Map {
+    fn width(&self) -> i32 {
+        Map::width(self)
+    }
+
+    fn height(&self) -> i32 {
+        Map::height(self)
+    }
+
+    fn is_transparent(&self, x: i32, y: i32) -> bool {
+        !self.at(x, y).block_sight
+    }
+
+    fn is_walkable(&self, x: i32, y: i32) -> bool {
+        !self.at(x, y).blocked
+    }
+
+    fn move_cost(&self, x: i32, y: i32) -> u32 {
+        self.at(x, y).kind.move_cost()
+    }
+
+    fn is_openable(&self, x: i32, y: i32) -> bool {
+        let tile = self.at(x, y);
+        tile.blocked && tile.is_passable()
+    }
+}
+
+//A flat copy of the transparency / walkability / move cost (and if it can be opened) of every cell in a map.
+//It is kept up to date by sync() which only rereads the cells the map reported as changed,
+//so opening a door or digging a wall is picked up without rebuilding everything.
+pub struct TerrainGrid {
+    width: i32,
+    height: i32,
+    transparent: Vec<bool>,
+    walkable: Vec<bool>,
+    move_cost: Vec<u32>,
+    openable: Vec<bool>,
+}
+
+impl TerrainGrid {
+    pub fn new(map: &Map) -> Self {
+        let size = (map.width() * map.height()) as usize;
+        let mut grid = TerrainGrid {
+            width: map.width(),
+            height: map.height(),
+            transparent: vec![false; size],
+            walkable: vec![false; size],
+            move_cost: vec![0; size],
+            openable: vec![false; size],
+        };
+        for y in 0 .. map.height() {
+            for x in 0 .. map.width() {
+                grid.update_cell(map, x, y);
+            }
+        }
+        grid
+    }
+
+    /// Pull in the cells that changed on the map since the last sync.
+    /// Returns true if any of them now blocks sight or movement diffrently (or costs more / less to walk on).
+    pub fn sync(&mut self, map: &mut Map) -> bool {
+        let mut changed = false;
+        for (x, y) in map.take_changes() {
+            changed |= self.update_cell(map, x, y);
+        }
+        changed
+    }
+
+    fn update_cell(&mut self, map: &Map, x: i32, y: i32) -> bool {
+        let index = (y * self.width + x) as usize;
+        let transparent = Terrain::is_transparent(map, x, y);
+        let walkable = Terrain::is_walkable(map, x, y);
+        let move_cost = Terrain::move_cost(map, x, y);
+        let openable = Terrain::is_openable(map, x, y);
+        let changed = self.transparent[index] != transparent || self.walkable[index] != walkable
+            || self.move_cost[index] != move_cost || self.openable[index] != openable;
+        self.transparent[index] = transparent;
+        self.walkable[index] = walkable;
+        self.move_cost[index] = move_cost;
+        self.openable[index] = openable;
+        changed
+    }
+}
+
+impl Terrain for TerrainGrid {
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn is_transparent(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.transparent[(y * self.width + x) as usize]
+    }
+
+    fn is_walkable(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.walkable[(y * self.width + x) as usize]
+    }
+
+    fn move_cost(&self, x: i32, y: i32) -> u32 {
+        if self.in_bounds(x, y) { self.move_cost[(y * self.width + x) as usize] } else { 0 }
+    }
+
+    fn is_openable(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.openable[(y * self.width + x) as usize]
+    }
+}
@@ -1,24 +1,207 @@
+use std::char;
 
+use tcod::Color;
+use tcod::colors;
 
+use game::draw_info::TileDrawInfo;
 
+const COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
+const COLOR_DARK_GROUND: Color = Color { r: 50, g: 50, b: 150 };
+const COLOR_LIGHT_WALL: Color = Color { r: 130, g: 110, b: 50 };
+const COLOR_LIGHT_GROUND: Color = Color { r: 200, g: 180, b: 50 };
+const COLOR_DARK_GRASS: Color = Color { r: 20, g: 60, b: 40 };
+const COLOR_LIGHT_GRASS: Color = Color { r: 70, g: 150, b: 40 };
+const COLOR_DARK_FOREST: Color = Color { r: 10, g: 40, b: 30 };
+const COLOR_LIGHT_FOREST: Color = Color { r: 20, g: 90, b: 20 };
+const COLOR_DARK_SHALLOW_WATER: Color = Color { r: 40, g: 60, b: 130 };
+const COLOR_LIGHT_SHALLOW_WATER: Color = Color { r: 80, g: 140, b: 220 };
+const COLOR_DARK_WATER: Color = Color { r: 20, g: 30, b: 110 };
+const COLOR_LIGHT_WATER: Color = Color { r: 40, g: 90, b: 200 };
+const COLOR_DARK_LAVA: Color = Color { r: 90, g: 20, b: 10 };
+const COLOR_LIGHT_LAVA: Color = Color { r: 220, g: 70, b: 10 };
+const COLOR_DARK_MOUNTAIN: Color = Color { r: 50, g: 50, b: 70 };
+const COLOR_LIGHT_MOUNTAIN: Color = Color { r: 120, g: 110, b: 100 };
+const COLOR_DOOR: Color = Color { r: 140, g: 90, b: 40 };
+
+//the libtcod tutorial tileset has walls at 256 and floor at 257 (see draw_info.rs)
+const WALL_TILE: u32 = 256;
+const FLOOR_TILE: u32 = 257;
+const STAIRS_DOWN_TILE: u32 = 265;
+
+//Damage per turn for standing in lava.
+const LAVA_DAMAGE: i32 = 5;
+
+//What a tile is made of. Everything about a tile that isn't explored comes from its kind,
+//so changing a tile (like opening a door) is just map.set(x, y, Tile::new(new_kind)).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileKind {
+    Floor,
+    Wall,
+    OpenDoor,
+    ClosedDoor,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Rubble,
+    StairsDown,
+    StairsUp,
+    Grass,
+    Forest,
+    Mountain,
+}
+
+impl TileKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TileKind::Floor => "floor",
+            TileKind::Wall => "wall",
+            TileKind::OpenDoor => "open door",
+            TileKind::ClosedDoor => "closed door",
+            TileKind::ShallowWater => "shallow water",
+            TileKind::DeepWater => "deep water",
+            TileKind::Lava => "lava",
+            TileKind::Rubble => "rubble",
+            TileKind::StairsDown => "stairs down",
+            TileKind::StairsUp => "stairs up",
+            TileKind::Grass => "grass",
+            TileKind::Forest => "forest",
+            TileKind::Mountain => "mountain",
+        }
+    }
+
+    pub fn walkable(self) -> bool {
+        match self {
+            TileKind::Wall | TileKind::ClosedDoor | TileKind::DeepWater | TileKind::Mountain => false,
+            _ => true,
+        }
+    }
+
+    pub fn transparent(self) -> bool {
+        match self {
+            TileKind::Wall | TileKind::ClosedDoor | TileKind::Forest | TileKind::Mountain => false,
+            _ => true,
+        }
+    }
+
+    //What walking onto the tile costs on top of the step itself, in pathfinding units (10 per tile).
+    //Lava is walkable but nothing with any sense goes through it if there is another way.
+    pub fn move_cost(self) -> u32 {
+        match self {
+            TileKind::ShallowWater | TileKind::Rubble => 10,
+            TileKind::Forest => 5,
+            TileKind::Lava => 100,
+            _ => 0,
+        }
+    }
+
+    //What the tile turns into when something walks into it, if anything, and the verb
+    //for the message about it. (doors get opened)
+    pub fn bumped(self) -> Option<(TileKind, &'static str)> {
+        match self {
+            TileKind::ClosedDoor => Some((TileKind::OpenDoor, "opens")),
+            _ => None,
+        }
+    }
+
+    //Damage for every turn something spends standing on the tile.
+    pub fn damage(self) -> i32 {
+        match self {
+            TileKind::Lava => LAVA_DAMAGE,
+            _ => 0,
+        }
+    }
+
+    //For the arial10x10 font.
+    pub fn ascii(self) -> TileDrawInfo {
+        match self {
+            TileKind::Floor => TileDrawInfo::new(' ', colors::WHITE, COLOR_LIGHT_GROUND, COLOR_DARK_GROUND),
+            TileKind::Wall => TileDrawInfo::new(' ', colors::WHITE, COLOR_LIGHT_WALL, COLOR_DARK_WALL),
+            TileKind::OpenDoor => TileDrawInfo::new('\'', COLOR_DOOR, COLOR_LIGHT_GROUND, COLOR_DARK_GROUND),
+            TileKind::ClosedDoor => TileDrawInfo::new('+', COLOR_DOOR, COLOR_LIGHT_WALL, COLOR_DARK_WALL),
+            TileKind::ShallowWater => TileDrawInfo::new('~', colors::WHITE, COLOR_LIGHT_SHALLOW_WATER, COLOR_DARK_SHALLOW_WATER),
+            TileKind::DeepWater => TileDrawInfo::new('~', colors::LIGHT_BLUE, COLOR_LIGHT_WATER, COLOR_DARK_WATER),
+            TileKind::Lava => TileDrawInfo::new('~', colors::YELLOW, COLOR_LIGHT_LAVA, COLOR_DARK_LAVA),
+            TileKind::Rubble => TileDrawInfo::new(':', colors::GREY, COLOR_LIGHT_GROUND, COLOR_DARK_GROUND),
+            TileKind::StairsDown => TileDrawInfo::new('>', colors::WHITE, COLOR_LIGHT_GROUND, COLOR_DARK_GROUND),
+            TileKind::StairsUp => TileDrawInfo::new('<', colors::WHITE, COLOR_LIGHT_GROUND, COLOR_DARK_GROUND),
+            TileKind::Grass => TileDrawInfo::new(' ', colors::WHITE, COLOR_LIGHT_GRASS, COLOR_DARK_GRASS),
+            TileKind::Forest => TileDrawInfo::new('T', colors::DARK_GREEN, COLOR_LIGHT_FOREST, COLOR_DARK_FOREST),
+            TileKind::Mountain => TileDrawInfo::new('^', colors::LIGHT_GREY, COLOR_LIGHT_MOUNTAIN, COLOR_DARK_MOUNTAIN),
+        }
+    }
+
+    //For the TiledFont font. Kinds without a tile of their own look the same as in ascii.
+    pub fn tileset(self) -> TileDrawInfo {
+        let ascii = self.ascii();
+        let tile = |index: u32| char::from_u32(index).unwrap();
+        match self {
+            TileKind::Floor => TileDrawInfo { glyph: tile(FLOOR_TILE), ..ascii },
+            TileKind::Wall => TileDrawInfo { glyph: tile(WALL_TILE), ..ascii },
+            TileKind::StairsDown => TileDrawInfo { glyph: tile(STAIRS_DOWN_TILE), ..ascii },
+            _ => ascii,
+        }
+    }
+
+    //How the kind is written in save files and drawn in prefabs.
+    pub fn to_char(self) -> char {
+        match self {
+            TileKind::Floor => '.',
+            TileKind::Wall => '#',
+            TileKind::OpenDoor => '/',
+            TileKind::ClosedDoor => '+',
+            TileKind::ShallowWater => '=',
+            TileKind::DeepWater => '~',
+            TileKind::Lava => '&',
+            TileKind::Rubble => ':',
+            TileKind::StairsDown => '>',
+            TileKind::StairsUp => '<',
+            TileKind::Grass => '"',
+            TileKind::Forest => 'T',
+            TileKind::Mountain => '^',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<TileKind> {
+        ALL_TILE_KINDS.iter().cloned().find(|kind| kind.to_char() == c)
+    }
+}
+
+pub const ALL_TILE_KINDS: [TileKind; 13] = [
+    TileKind::Floor, TileKind::Wall, TileKind::OpenDoor, TileKind::ClosedDoor,
+    TileKind::ShallowWater, TileKind::DeepWater, TileKind::Lava, TileKind::Rubble,
+    TileKind::StairsDown, TileKind::StairsUp, TileKind::Grass, TileKind::Forest, TileKind::Mountain,
+];
+
+//blocked / block_sight always match the kind when the tile is made with new(),
+//they are kept on the tile since they get checked all over the place.
 #[derive(Clone, Copy, Debug)]
 pub struct Tile {
     pub blocked: bool,
     pub block_sight: bool,
     pub explored: bool,
+    pub kind: TileKind,
 }
 
 impl Tile {
+    pub fn new(kind: TileKind) -> Self {
+        Tile{blocked: !kind.walkable(), block_sight: !kind.transparent(), explored: false, kind: kind}
+    }
 
     pub fn is_wall(self) -> bool {
-        return self.block_sight && self.block_sight;
+        self.kind == TileKind::Wall
+    }
+
+    //Can be walked through, possibly after opening it first.
+    //Used when checking that every part of a map can be reached.
+    pub fn is_passable(self) -> bool {
+        !self.blocked || self.kind.bumped().map_or(false, |(kind, _)| kind.walkable())
     }
 
     pub fn empty() -> Self {
-        Tile{blocked: false, block_sight: false, explored: false}
+        Tile::new(TileKind::Floor)
     }
 
     pub fn wall() -> Self {
-        Tile{blocked: true, block_sight: true, explored: false}
+        Tile::new(TileKind::Wall)
     }
-}
\ No newline at end of file
+}
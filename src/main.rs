@@ -3,6 +3,8 @@ extern crate lazy_static;
 extern crate tcod;
 extern crate rand;
 
+use std::env;
+
 mod game;
 mod week_01;
 mod week_02;
@@ -10,5 +12,20 @@ mod week_03;
 mod week_04;
 
 fn main() {
-    game::run();
+    //pass --seed <number> to start a new game on a specific dungeon.
+    //Otherwise continue the saved game if there is one.
+    //--map <name> picks how new games build their levels (caves by default).
+    let args: Vec<String> = env::args().collect();
+    let map = match args.iter().position(|arg| arg == "--map") {
+        Some(index) => args.get(index + 1).expect("--map needs a value").as_str(),
+        None => "caves",
+    };
+    match args.iter().position(|arg| arg == "--seed") {
+        Some(index) => {
+            let value = args.get(index + 1).expect("--seed needs a value");
+            let seed = value.parse().expect("--seed must be a positive number");
+            game::run(seed, map);
+        }
+        None => game::run_saved(game::random_seed(), map),
+    }
 }